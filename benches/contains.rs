@@ -0,0 +1,72 @@
+use std::fmt;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::prelude::*;
+
+use apbf::{Layout, APBF};
+
+const ELEMENTS: u64 = 1024;
+
+struct Setting {
+    k: usize,
+    l: usize,
+    m: usize,
+    layout: Layout,
+}
+
+impl Setting {
+    fn new(k: usize, l: usize, m: usize, layout: Layout) -> Self {
+        Setting { k, l, m, layout }
+    }
+}
+
+impl fmt::Display for Setting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "k={}, l={}, m={}, layout={:?}",
+            self.k, self.l, self.m, self.layout
+        )
+    }
+}
+
+fn bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("contains");
+
+    let mut settings = Vec::new();
+    for &layout in &[Layout::Sliced, Layout::Blocked] {
+        settings.push(Setting::new(10, 7, 1024, layout));
+        settings.push(Setting::new(10, 7, 1 << 20, layout));
+        settings.push(Setting::new(14, 11, 1024, layout));
+        settings.push(Setting::new(14, 11, 1 << 20, layout));
+    }
+
+    group.throughput(Throughput::Elements(ELEMENTS));
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let input = (0..ELEMENTS).map(|_| rng.gen()).collect::<Vec<usize>>();
+
+    for s in &settings {
+        let mut apbf = APBF::builder(s.k, s.l, s.m).layout(s.layout).build();
+        for _ in 0..(apbf.window() + apbf.slack()) {
+            apbf.insert(rng.gen::<usize>());
+        }
+        group.bench_with_input(BenchmarkId::new("size", s), s, |b, _| {
+            b.iter(|| input.iter().filter(|&&n| apbf.contains(n)).count())
+        });
+
+        // Values held by the structure, which are probed on `k` slices at least.
+        for &n in &input {
+            apbf.insert(n);
+        }
+        let held = &input[input.len() - std::cmp::min(apbf.window() as usize, input.len())..];
+        group.throughput(Throughput::Elements(held.len() as u64));
+        group.bench_with_input(BenchmarkId::new("held", s), s, |b, _| {
+            b.iter(|| held.iter().filter(|&&n| apbf.contains(n)).count())
+        });
+        group.throughput(Throughput::Elements(ELEMENTS));
+    }
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
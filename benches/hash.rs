@@ -0,0 +1,42 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::distributions::Alphanumeric;
+use rand::prelude::*;
+
+use apbf::hash::km::KMHashers;
+use apbf::hash::split::SplitHashers;
+use apbf::hash::Hashers;
+
+const ELEMENTS: u64 = 1024;
+
+fn bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hash");
+    group.throughput(Throughput::Elements(ELEMENTS));
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    for &len in &[8, 64, 512] {
+        let input = (0..ELEMENTS)
+            .map(|_| (&mut rng).sample_iter(Alphanumeric).take(len).collect())
+            .collect::<Vec<String>>();
+
+        let km = KMHashers::new(1024);
+        group.bench_with_input(BenchmarkId::new("km", len), &input, |b, input| {
+            b.iter(|| {
+                for s in input {
+                    black_box(km.hash::<String, _>(s));
+                }
+            })
+        });
+
+        let split = SplitHashers::new(1024);
+        group.bench_with_input(BenchmarkId::new("split", len), &input, |b, input| {
+            b.iter(|| {
+                for s in input {
+                    black_box(split.hash::<String, _>(s));
+                }
+            })
+        });
+    }
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -3,7 +3,7 @@ use std::fmt;
 use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
 use rand::prelude::*;
 
-use apbf::APBF;
+use apbf::{Layout, APBF};
 
 const ELEMENTS: u64 = 1024;
 
@@ -35,6 +35,8 @@ fn bench(c: &mut Criterion) {
         Setting::new(14, 11, 64),
         Setting::new(14, 11, 256),
         Setting::new(14, 11, 1024),
+        Setting::new(10, 7, 1 << 20),
+        Setting::new(14, 11, 1 << 20),
     ];
 
     group.throughput(Throughput::Elements(ELEMENTS));
@@ -42,8 +44,8 @@ fn bench(c: &mut Criterion) {
     let mut rng = StdRng::from_seed([0u8; 32]);
     let input = (0..ELEMENTS).map(|_| rng.gen()).collect::<Vec<usize>>();
 
-    for s in settings {
-        group.bench_with_input(BenchmarkId::new("size", &s), &s, |b, s| {
+    for s in &settings {
+        group.bench_with_input(BenchmarkId::new("size", s), s, |b, s| {
             b.iter_batched(
                 || APBF::new(s.k, s.l, s.m),
                 |mut apbf| {
@@ -54,6 +56,28 @@ fn bench(c: &mut Criterion) {
                 BatchSize::SmallInput,
             )
         });
+        group.bench_with_input(BenchmarkId::new("pow2", s), s, |b, s| {
+            b.iter_batched(
+                || APBF::new_pow2(s.k, s.l, s.m),
+                |mut apbf| {
+                    for &n in &input {
+                        apbf.insert(n);
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("blocked", s), s, |b, s| {
+            b.iter_batched(
+                || APBF::builder(s.k, s.l, s.m).layout(Layout::Blocked).build(),
+                |mut apbf| {
+                    for &n in &input {
+                        apbf.insert(n);
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
     }
 }
 
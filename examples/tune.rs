@@ -0,0 +1,59 @@
+//! Recommends parameters of an APBF for a window and a false positive rate.
+//!
+//! ```text
+//! cargo run --release --example tune -- <window> <fpr>
+//! ```
+
+use std::env;
+use std::process;
+
+use apbf::analysis::{self, Parameters};
+use apbf::APBF;
+use rand::prelude::*;
+
+fn usage() -> ! {
+    eprintln!("usage: tune <window> <fpr>");
+    process::exit(2);
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.len() != 2 {
+        usage();
+    }
+    let window = args[0].parse::<u64>().unwrap_or_else(|_| usage());
+    let fpr = args[1].parse::<f64>().unwrap_or_else(|_| usage());
+
+    let Parameters { k, l, m } = match analysis::required_slices(fpr, window) {
+        Some(p) => p,
+        None => {
+            eprintln!("no parameters found for window {} and fpr {}", window, fpr);
+            process::exit(1);
+        }
+    };
+    let g = analysis::generation(k, m);
+    println!("k = {}, l = {}, m = {}", k, l, m);
+    println!("generation: {}", g);
+    println!("window: {}, slack: {}", l as u64 * g, k as u64 * g);
+    println!("memory: {} bytes", analysis::estimated_memory(k, l, m));
+    println!(
+        "theoretical false positive rate: {:.3e}",
+        analysis::false_positive_rate(k, l, m)
+    );
+
+    // Fill every slice with random values, and query values never inserted.
+    let mut apbf: APBF<u64, _> = APBF::new(k, l, m);
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    for _ in 0..(k + l) as u64 * g {
+        apbf.insert(rng.gen::<u64>());
+    }
+    let queries = 1_000_000;
+    let fp = (0..queries)
+        .filter(|_| apbf.contains(rng.gen::<u64>()))
+        .count();
+    println!(
+        "simulated false positive rate: {:.3e} ({} queries)",
+        fp as f64 / queries as f64,
+        queries
+    );
+}
@@ -0,0 +1,87 @@
+#ifndef APBF_H
+#define APBF_H
+
+/* Generated with cbindgen. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * An opaque APBF instance holding byte strings.
+ */
+typedef struct Apbf Apbf;
+
+/**
+ * Creates a new filter with parameters `k`, `l` and `m`.
+ * Returns `NULL` if any of them is zero.
+ */
+Apbf *apbf_new(size_t k, size_t l, size_t m);
+
+/**
+ * Releases a filter. Does nothing if `apbf` is `NULL`.
+ *
+ * # Safety
+ *
+ * `apbf` must be `NULL` or a pointer returned by [`apbf_new`] or
+ * [`apbf_load`] which has not been released yet.
+ */
+void apbf_free(Apbf *apbf);
+
+/**
+ * Inserts the `len` bytes at `data`.
+ *
+ * # Safety
+ *
+ * `apbf` must be a valid filter, and `data` must point to `len` readable
+ * bytes unless `len` is zero.
+ */
+void apbf_insert_bytes(Apbf *apbf, const uint8_t *data, size_t len);
+
+/**
+ * Returns `true` if the filter holds the `len` bytes at `data`.
+ *
+ * # Safety
+ *
+ * Same as [`apbf_insert_bytes`].
+ */
+bool apbf_contains_bytes(const Apbf *apbf, const uint8_t *data, size_t len);
+
+/**
+ * Adds the values held by `src` into `dst`. Returns `false` without
+ * modifying `dst` unless they have the same parameters and seed, and have
+ * shifted the same number of times, or if they are the same filter.
+ * See [`APBF::merge`].
+ *
+ * # Safety
+ *
+ * `dst` and `src` must be valid filters. They may be the same pointer,
+ * which is rejected before either is dereferenced.
+ */
+bool apbf_merge(Apbf *dst, const Apbf *src);
+
+/**
+ * Writes the state of the filter to `buf` if `cap` is at least the size
+ * of the dump, and returns the size in bytes.
+ * Call it with a `NULL` buffer to query the size.
+ *
+ * # Safety
+ *
+ * `apbf` must be a valid filter, and `buf` must be `NULL` or point to
+ * `cap` writable bytes.
+ */
+size_t apbf_dump(const Apbf *apbf, uint8_t *buf, size_t cap);
+
+/**
+ * Creates a filter from the `len` bytes at `data` written by [`apbf_dump`].
+ * Returns `NULL` if they are not a valid dump.
+ *
+ * # Safety
+ *
+ * `data` must point to `len` readable bytes unless `len` is zero.
+ */
+Apbf *apbf_load(const uint8_t *data, size_t len);
+
+#endif /* APBF_H */
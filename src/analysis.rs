@@ -0,0 +1,191 @@
+//! Theoretical properties of APBF described in the original paper,
+//! which help to choose parameters of a filter.
+
+use std::collections::hash_map::RandomState;
+use std::f64::consts::LN_2;
+use std::mem;
+
+use crate::core::{APBF, WORD_BITS};
+use crate::hash::km::KMHashers;
+
+/// Parameters of an APBF. See [`APBF`](crate::APBF) for their meaning.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parameters {
+    pub k: usize,
+    pub l: usize,
+    pub m: usize,
+}
+
+const MAX_K: usize = 64;
+
+/// Returns the number of insertions for each generation, which is chosen
+/// so that a slice is half full when it stops receiving insertions.
+pub fn generation(k: usize, m: usize) -> u64 {
+    ((m as f64) * LN_2 / (k as f64)) as u64
+}
+
+/// Returns the false positive rate of an APBF with given parameters.
+///
+/// The rate reaches its maximum right before the logical slices shift,
+/// where the i-th slice has received `min(i + 1, k)` generations of insertions.
+/// This computes the rate at that point.
+pub fn false_positive_rate(k: usize, l: usize, m: usize) -> f64 {
+    false_positive_rate_with_generation(k, l, m, generation(k, m))
+}
+
+/// Returns the false positive rate of an APBF with given parameters
+/// and a custom generation size `g`.
+/// See [`false_positive_rate`] for details.
+pub fn false_positive_rate_with_generation(k: usize, l: usize, m: usize, g: u64) -> f64 {
+    let g = g as f64;
+    let m = m as f64;
+    let ratios = (0..k + l).map(|i| {
+        let n = (std::cmp::min(i + 1, k) as f64) * g;
+        1.0 - (1.0 - 1.0 / m).powf(n)
+    });
+    run_probability(k, ratios)
+}
+
+/// Returns the number of bytes taken by an APBF with given parameters
+/// created by [`APBF::new`](crate::APBF::new), as reported by
+/// [`APBF::memory_usage`](crate::APBF::memory_usage), without allocating it.
+///
+/// [`Clearing::Amortized`](crate::Clearing::Amortized) takes
+/// `8 * (ceil(m / 64) + 1)` more bytes for the spare slice.
+pub fn estimated_memory(k: usize, l: usize, m: usize) -> usize {
+    let s = k + l;
+    let words = s * m.div_ceil(WORD_BITS) + s;
+    mem::size_of::<APBF<(), KMHashers<RandomState, RandomState>>>() + words * mem::size_of::<u64>()
+}
+
+/// Returns parameters of the smallest APBF which remembers at least
+/// `window` latest insertions with false positive rate not exceeding `fpr`.
+///
+/// For each `k` up to 64, the largest `l` satisfying the rate is chosen,
+/// and `m` is derived from the generation size `window / l`.
+/// Returns `None` if no such parameters are found.
+pub fn required_slices(fpr: f64, window: u64) -> Option<Parameters> {
+    if !(fpr > 0.0 && fpr < 1.0) || window == 0 {
+        return None;
+    }
+
+    let size = |p: &Parameters| (p.k + p.l) * p.m;
+    let mut best: Option<Parameters> = None;
+    for k in 1..=MAX_K {
+        // The size is larger than k * window / ln 2 for any l.
+        let lower_bound = (window as f64) * (k as f64) / LN_2;
+        if best.is_some_and(|b| lower_bound >= size(&b) as f64) {
+            break;
+        }
+
+        let params = |l: usize| {
+            let g = window.div_ceil(l as u64);
+            let m = ((g * k as u64) as f64 / LN_2).ceil() as usize;
+            Parameters { k, l, m }
+        };
+        let satisfies = |l: usize| {
+            let p = params(l);
+            false_positive_rate(p.k, p.l, p.m) <= fpr
+        };
+        if !satisfies(1) {
+            continue;
+        }
+
+        // The rate increases with l. Find the largest l satisfying it
+        // by doubling l, and then by binary search in [lo, hi).
+        let max_l = window as usize;
+        let mut lo = 1;
+        let mut hi = 2;
+        while hi <= max_l && satisfies(hi) {
+            lo = hi;
+            hi *= 2;
+        }
+        let mut hi = std::cmp::min(hi, max_l + 1);
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            if satisfies(mid) {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+
+        let p = params(lo);
+        if best.is_none_or(|b| size(&p) < size(&b)) {
+            best = Some(p);
+        }
+    }
+    best
+}
+
+// Returns the probability that a sequence of independent Bernoulli trials
+// with given success probabilities contains `k` consecutive successes.
+pub(crate) fn run_probability<I>(k: usize, probs: I) -> f64
+where
+    I: IntoIterator<Item = f64>,
+{
+    // dist[r] is the probability that the current run has length r
+    // and no run of length k has appeared yet.
+    let mut dist = vec![0.0; k];
+    let mut next = vec![0.0; k];
+    dist[0] = 1.0;
+    let mut found = 0.0;
+    for q in probs {
+        next[0] = 0.0;
+        for (r, &d) in dist.iter().enumerate() {
+            next[0] += d * (1.0 - q);
+            if r + 1 == k {
+                found += d * q;
+            } else {
+                next[r + 1] = d * q;
+            }
+        }
+        std::mem::swap(&mut dist, &mut next);
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_probability() {
+        assert_eq!(run_probability(2, vec![1.0, 1.0]), 1.0);
+        assert_eq!(run_probability(2, vec![1.0, 0.0, 1.0]), 0.0);
+        assert!((run_probability(1, vec![0.5, 0.5]) - 0.75).abs() < 1e-12);
+        assert!((run_probability(2, vec![0.5, 0.5, 0.5]) - 0.375).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_false_positive_rate() {
+        let fpr = false_positive_rate(10, 7, 1 << 20);
+        assert!((0.001..0.002).contains(&fpr), "fpr {}", fpr);
+        let fpr = false_positive_rate(14, 11, 1 << 20);
+        assert!((0.0001..0.0002).contains(&fpr), "fpr {}", fpr);
+
+        assert!(false_positive_rate(10, 8, 1024) > false_positive_rate(10, 7, 1024));
+        assert!(false_positive_rate(11, 7, 1024) < false_positive_rate(10, 7, 1024));
+
+        let g = generation(10, 1024);
+        assert!(
+            false_positive_rate_with_generation(10, 7, 1024, g * 2)
+                > false_positive_rate(10, 7, 1024)
+        );
+        assert!(
+            false_positive_rate_with_generation(10, 7, 1024, g / 2)
+                < false_positive_rate(10, 7, 1024)
+        );
+    }
+
+    #[test]
+    fn test_required_slices() {
+        let window = 100_000;
+        let p = required_slices(0.001, window).unwrap();
+        assert!(false_positive_rate(p.k, p.l, p.m) <= 0.001);
+        assert!(p.l as u64 * generation(p.k, p.m) >= window);
+
+        assert_eq!(required_slices(0.0, window), None);
+        assert_eq!(required_slices(1.0, window), None);
+    }
+}
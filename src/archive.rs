@@ -0,0 +1,188 @@
+//! Archived representation of an APBF which can be queried in place.
+//!
+//! [`APBF::to_archive`] serializes a structure into bytes, which
+//! [`ArchivedAPBF`] reads without copying the bit array. An archive that is
+//! `mmap`ed or received over the network can be queried immediately.
+//!
+//! The layout of an archive is the same as the files created by the `mmap`
+//! feature, except that every word is stored in little-endian.
+//! Archives need no alignment.
+
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::io;
+use std::marker::PhantomData;
+
+use zerocopy::byteorder::little_endian::U64;
+use zerocopy::FromBytes;
+
+use crate::core::{search, Layout, APBF, BLOCK_HASH, WORD_BITS};
+use crate::format::*;
+use crate::hash::{Hashers, Hashes};
+use crate::store::BitStore;
+
+/// A read-only view of an APBF archive borrowing its bytes.
+pub struct ArchivedAPBF<'a, T: ?Sized, H> {
+    hashers: H,
+    k: usize,
+    l: usize,
+    w: usize,
+    s: usize,
+    p: usize,
+    layout: Layout,
+    seed: Option<u64>,
+    ids: &'a [U64],
+    words: &'a [U64],
+    _t: PhantomData<T>,
+}
+
+impl<'a, T, H> ArchivedAPBF<'a, T, H>
+where
+    T: Hash + ?Sized,
+    H: Hashers,
+{
+    /// Reads an archive created by [`APBF::to_archive`].
+    /// `hashers` must compute the same hash values as those of the archived
+    /// structure.
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if `bytes`
+    /// is not a valid archive.
+    pub fn from_bytes(bytes: &'a [u8], hashers: H) -> io::Result<Self> {
+        let words = <[U64]>::ref_from_bytes(bytes)
+            .map_err(|_| invalid(format!("unexpected archive size {}", bytes.len())))?;
+        if words.len() < HEADER_WORDS {
+            return Err(invalid("archive is too short".to_string()));
+        }
+        let mut header = [0; HEADER_WORDS];
+        for (h, w) in header.iter_mut().zip(words) {
+            *h = w.get();
+        }
+        let header = Header::decode(&header)?;
+        if words.len() != HEADER_WORDS + header.body_words() {
+            return Err(invalid(format!("unexpected archive size {}", bytes.len())));
+        }
+
+        let s = header.s();
+        let (ids, words) = words[HEADER_WORDS..].split_at(s);
+        Ok(ArchivedAPBF {
+            hashers,
+            k: header.k,
+            l: header.l,
+            w: header.w(),
+            s,
+            p: header.p,
+            layout: header.layout,
+            seed: header.seed,
+            ids,
+            words,
+            _t: PhantomData,
+        })
+    }
+
+    /// Returns `true` if the archived structure holds a given value.
+    pub fn contains<V>(&self, value: V) -> bool
+    where
+        V: Borrow<T>,
+    {
+        let hashes = self.hashers.hash(value);
+        let block = match self.layout {
+            Layout::Sliced => 0,
+            Layout::Blocked => hashes.get(BLOCK_HASH) as usize / WORD_BITS * self.s,
+        };
+        search(self.k, self.l, |i| {
+            let pos = self.p + i;
+            let pos = pos.checked_sub(self.s).unwrap_or(pos);
+            let h = hashes.get(self.ids[pos].get()) as usize;
+            let p = match self.layout {
+                Layout::Sliced => pos * self.w + h / WORD_BITS,
+                Layout::Blocked => block + pos,
+            };
+            self.words[p].get() & (1 << (h % WORD_BITS)) != 0
+        })
+    }
+
+    /// Returns the seed of the hash functions stored in the archive,
+    /// or `None` if the archived structure did not know it.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+}
+
+impl<T: ?Sized, H: Hashers, S: BitStore> APBF<T, H, S> {
+    /// Serializes the structure into an archive readable by [`ArchivedAPBF`].
+    /// The hash functions are not included.
+    ///
+    /// It is the same as [`APBF::to_bytes`].
+    pub fn to_archive(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Clearing;
+    use crate::hash::km::KMHashers;
+    use crate::Builder;
+
+    #[test]
+    fn test_archive_contains() {
+        for &layout in &[Layout::Sliced, Layout::Blocked] {
+            for &clearing in &[Clearing::Eager, Clearing::Amortized] {
+                let hashers = KMHashers::new(1024);
+                let mut apbf: APBF<u64, _> = Builder::new(4, 3, 1024)
+                    .hashers(hashers.clone())
+                    .layout(layout)
+                    .clearing(clearing)
+                    .build();
+                for v in 0..2000 {
+                    apbf.insert(v);
+                }
+
+                let bytes = apbf.to_archive();
+                let archived = ArchivedAPBF::from_bytes(&bytes, hashers).unwrap();
+                for v in 0..4000 {
+                    assert_eq!(archived.contains(v), apbf.contains(v));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_archive_unaligned() {
+        let hashers = KMHashers::new(1000);
+        let mut apbf: APBF<u64, _> = APBF::with_hashers(4, 3, 1000, hashers.clone());
+        for v in 0..100 {
+            apbf.insert(v);
+        }
+
+        let mut buf = vec![0u8];
+        buf.extend(apbf.to_archive());
+        let archived = ArchivedAPBF::<u64, _>::from_bytes(&buf[1..], hashers).unwrap();
+        for v in 0..100 {
+            assert!(archived.contains(v));
+        }
+    }
+
+    #[test]
+    fn test_archive_invalid() {
+        let apbf: APBF<u64, _> = APBF::new(4, 3, 1000);
+        let bytes = apbf.to_archive();
+        for b in &[&bytes[..bytes.len() - 8], &bytes[..100], &[0u8; 128][..]] {
+            let err = ArchivedAPBF::<u64, _>::from_bytes(b, KMHashers::new(1000))
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_archive_unsized() {
+        let hashers = KMHashers::new(1000);
+        let mut apbf: APBF<str, _> = APBF::with_hashers(4, 3, 1000, hashers.clone());
+        apbf.insert("apbf");
+        let bytes = apbf.to_archive();
+        let archived = ArchivedAPBF::<str, _>::from_bytes(&bytes, hashers).unwrap();
+        assert!(archived.contains("apbf"));
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::hash_map::RandomState;
+use std::convert::Infallible;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::core::{
+    Clearing, Layout, Saturation, SaturationEvent, ShiftEvent, ShiftHook, APBF, WORD_BITS,
+};
+use crate::hash::km::KMHashers;
+use crate::hash::Hashers;
+use crate::store::BitStore;
+
+/// A builder to configure and create an [`APBF`] instance.
+///
+/// ```
+/// use apbf::{Builder, Layout, APBF};
+///
+/// let mut apbf: APBF<u64, _> = Builder::new(10, 7, 1024)
+///     .layout(Layout::Blocked)
+///     .build();
+/// apbf.insert(42);
+/// assert!(apbf.contains(42));
+/// ```
+pub struct Builder<T: ?Sized, H: Hashers> {
+    pub(crate) config: Config,
+    pub(crate) hashers: H,
+    _t: PhantomData<T>,
+}
+
+// Configuration of an APBF independent of hash functions.
+#[derive(Clone)]
+pub(crate) struct Config {
+    pub(crate) k: usize,
+    pub(crate) l: usize,
+    pub(crate) m: usize,
+    pub(crate) layout: Layout,
+    pub(crate) clearing: Clearing,
+    pub(crate) g: Option<u64>,
+    pub(crate) on_shift: Option<ShiftHook>,
+    pub(crate) on_saturation: Option<Saturation>,
+}
+
+impl<T: Hash + ?Sized> Builder<T, KMHashers<RandomState, RandomState>> {
+    /// Creates a builder for an APBF with given parameters.
+    /// See [`APBF`] for the meaning of `k`, `l` and `m`.
+    pub fn new(k: usize, l: usize, m: usize) -> Self {
+        Builder {
+            config: Config {
+                k,
+                l,
+                m,
+                layout: Layout::Sliced,
+                clearing: Clearing::Eager,
+                g: None,
+                on_shift: None,
+                on_saturation: None,
+            },
+            hashers: KMHashers::new(m as u64),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<T, H> Builder<T, H>
+where
+    T: Hash + ?Sized,
+    H: Hashers,
+{
+    /// Sets hash functions used by the filter.
+    /// Each of them should return values in range `[0, m)`.
+    pub fn hashers<H2: Hashers>(self, hashers: H2) -> Builder<T, H2> {
+        Builder {
+            config: self.config,
+            hashers,
+            _t: PhantomData,
+        }
+    }
+
+    /// Sets hash functions derived from two inner hash functions
+    /// with [`KMHashers`], e.g. seeded ones to get reproducible filters.
+    pub fn build_hashers<B1, B2>(self, bh1: B1, bh2: B2) -> Builder<T, KMHashers<B1, B2>>
+    where
+        B1: BuildHasher,
+        B2: BuildHasher,
+    {
+        let m = self.config.m as u64;
+        self.hashers(KMHashers::with_build_hashers(m, bh1, bh2))
+    }
+
+    /// Sets the memory layout of the slices. Defaults to [`Layout::Sliced`].
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.config.layout = layout;
+        self
+    }
+
+    /// Sets the strategy to clear retired slices. Defaults to [`Clearing::Eager`].
+    pub fn clearing(mut self, clearing: Clearing) -> Self {
+        self.config.clearing = clearing;
+        self
+    }
+
+    /// Sets the number of insertions for each generation, i.e. how many
+    /// insertions cause a shift of the logical slices.
+    ///
+    /// It defaults to `m * ln 2 / k`, which makes a slice half full when it
+    /// stops receiving insertions and minimizes the false positive rate
+    /// for the memory. A slice filled with `k * g` insertions has
+    /// the fill ratio of about `1 - exp(-k * g / m)`, so a larger `g`
+    /// widens the window at the cost of a higher false positive rate,
+    /// and a smaller one does the opposite.
+    /// See [`analysis::false_positive_rate_with_generation`](crate::analysis::false_positive_rate_with_generation)
+    /// to compute the rate.
+    pub fn generation(mut self, g: u64) -> Self {
+        self.config.g = Some(g);
+        self
+    }
+
+    /// Sets a function called whenever the logical slices shift.
+    /// See [`APBF::set_on_shift`].
+    pub fn on_shift<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&ShiftEvent) + Send + Sync + 'static,
+    {
+        self.config.on_shift = Some(Arc::new(f));
+        self
+    }
+
+    /// Sets thresholds of the fill ratio and the false positive rate,
+    /// and a function called on a shift exceeding either.
+    /// See [`APBF::set_on_saturation`].
+    ///
+    /// The crate does not depend on `log` or `tracing`, so the function
+    /// puts the fields of the event into those of its own logger:
+    ///
+    /// ```
+    /// # // A stand-in for `tracing::warn!` checking the fields.
+    /// # mod tracing {
+    /// #     macro_rules! fields {
+    /// #         ($($k:ident = $v:expr,)* $msg:literal) => {{ $(let _ = $v;)* }};
+    /// #     }
+    /// #     pub(crate) use fields as warn;
+    /// # }
+    /// let apbf = apbf::APBF::<u64, _>::builder(10, 7, 1 << 20)
+    ///     .on_saturation(0.6, 1e-3, |e| {
+    ///         tracing::warn!(
+    ///             generation = e.generation,
+    ///             fill_ratio = e.fill_ratio,
+    ///             estimated_fpr = e.estimated_false_positive_rate,
+    ///             k = e.k,
+    ///             l = e.l,
+    ///             m = e.m,
+    ///             g = e.g,
+    ///             "apbf is saturated"
+    ///         );
+    ///     })
+    ///     .build();
+    /// ```
+    pub fn on_saturation<F>(
+        mut self,
+        max_fill_ratio: f64,
+        max_false_positive_rate: f64,
+        f: F,
+    ) -> Self
+    where
+        F: Fn(&SaturationEvent) + Send + Sync + 'static,
+    {
+        self.config.on_saturation = Some(Saturation {
+            max_fill_ratio,
+            max_false_positive_rate,
+            hook: Arc::new(f),
+        });
+        self
+    }
+
+    /// Creates an APBF instance.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`Layout::Blocked`] is selected and `m` is not a multiple of 64,
+    /// or if the generation is set to zero.
+    pub fn build(self) -> APBF<T, H> {
+        APBF::from_builder(self)
+    }
+
+    /// Returns the number of words of the store required by
+    /// [`Builder::build_with_store`].
+    pub fn store_len(&self) -> usize {
+        let Config {
+            k, l, m, clearing, ..
+        } = self.config;
+        let s = match clearing {
+            Clearing::Eager => k + l,
+            Clearing::Amortized => k + l + 1,
+        };
+        s * m.div_ceil(WORD_BITS)
+    }
+
+    /// Creates an APBF instance whose bit array is kept in `store`.
+    /// The words of the store are cleared.
+    ///
+    /// ```
+    /// use apbf::{Builder, APBF};
+    ///
+    /// let builder = Builder::new(10, 7, 1024);
+    /// let mut buf = vec![0; builder.store_len()];
+    /// let mut apbf: APBF<u64, _, &mut [u64]> = builder.build_with_store(&mut buf[..]);
+    /// apbf.insert(42);
+    /// assert!(apbf.contains(42));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of the store differs from [`Builder::store_len`],
+    /// in addition to the cases of [`Builder::build`].
+    pub fn build_with_store<S: BitStore>(self, mut store: S) -> APBF<T, H, S> {
+        let len = self.store_len();
+        assert_eq!(
+            store.words().len(),
+            len,
+            "the store must have {} words",
+            len
+        );
+        for word in store.words_mut() {
+            *word = 0;
+        }
+        match APBF::try_from_builder(self, |_, _| Ok::<_, Infallible>(store)) {
+            Ok(apbf) => apbf,
+            Err(e) => match e {},
+        }
+    }
+}
@@ -1,12 +1,110 @@
 use std::borrow::Borrow;
 use std::collections::hash_map::RandomState;
+use std::convert::Infallible;
+use std::fmt;
 use std::hash::Hash;
 use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Range, RangeInclusive};
+use std::sync::Arc;
 
-use bitvec::prelude as bv;
+use crate::analysis::{self, run_probability};
+use crate::builder::{Builder, Config};
+use crate::hash::km::{KMHashers, Reduction};
+use crate::hash::sip::SipBuildHasher128;
+use crate::hash::split::SplitHashers;
+use crate::hash::{Hashers, Hashes, SeedableHashers};
+use crate::stats::Stats;
+use crate::store::BitStore;
 
-use crate::hash::km::KMHashers;
-use crate::hash::{Hashers, Hashes};
+pub(crate) const WORD_BITS: usize = 64;
+
+// Index of the hash function choosing a block in the blocked layout.
+// Indices of slices are assigned from zero, so it never collides with them.
+pub(crate) const BLOCK_HASH: u64 = u64::MAX;
+
+/// Memory layout of the slices on the underlying bit array.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// Each slice occupies a contiguous range of words.
+    /// An insertion touches `k` words spread across the whole array.
+    Sliced,
+    /// The `b`-th words of all slices are placed next to each other,
+    /// forming a block of `k + l` words. Every value is hashed into a single
+    /// block, so an insertion touches `k` words of the block regardless of
+    /// the size of the filter. Blocks are not aligned to cache lines, so
+    /// a block spans `(k + l) / 8` cache lines rounded up, plus one at most.
+    ///
+    /// Since the values are not evenly distributed across the blocks,
+    /// the false positive rate is higher than that of [`Layout::Sliced`]
+    /// with the same parameters. Each block receives only `64 * ln 2 / k`
+    /// values per generation on average, so the penalty grows with `k`:
+    /// in our measurements the false positive rate was about 2x for
+    /// `k = 10, l = 7` and about 4.5x for `k = 14, l = 11`.
+    /// It can be compensated by increasing `k`.
+    ///
+    /// This layout requires `m` to be a multiple of 64.
+    Blocked,
+}
+
+/// Strategy to clear the slice retired on each shift.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Clearing {
+    /// Clears the whole retired slice at once when the logical slices shift.
+    /// Every `g`-th insertion takes time proportional to `m`.
+    Eager,
+    /// Keeps one spare slice besides the `k + l` slices. The retired slice
+    /// becomes the spare and is cleared a few words at a time by the
+    /// insertions of the following generation, so that every insertion takes
+    /// roughly the same time. It costs one additional slice of memory.
+    Amortized,
+}
+
+/// Information on a shift of logical slices passed to the function
+/// set by [`APBF::set_on_shift`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ShiftEvent {
+    /// Number of shifts that have happened so far, including this one.
+    pub generation: u64,
+    /// Number of insertions in the generation which has just been completed.
+    pub inserts: u64,
+    /// Number of bits that were set in the retired slice.
+    pub retired_ones: usize,
+}
+
+pub(crate) type ShiftHook = Arc<dyn Fn(&ShiftEvent) + Send + Sync>;
+
+/// Information on a filter exceeding a threshold passed to the function
+/// set by [`APBF::set_on_saturation`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct SaturationEvent {
+    /// Number of shifts that have happened so far.
+    pub generation: u64,
+    /// Fill ratio of the slice which has just stopped receiving insertions.
+    pub fill_ratio: f64,
+    /// Estimated false positive rate at the shift.
+    /// See [`APBF::estimated_false_positive_rate`].
+    pub estimated_false_positive_rate: f64,
+    /// Parameter `k` of the filter.
+    pub k: usize,
+    /// Parameter `l` of the filter.
+    pub l: usize,
+    /// Parameter `m` of the filter.
+    pub m: usize,
+    /// Generation of the filter, the number of insertions between shifts.
+    pub g: u64,
+}
+
+// Thresholds of the fill ratio and the false positive rate, and the function
+// called when either is exceeded.
+#[derive(Clone)]
+pub(crate) struct Saturation {
+    pub(crate) max_fill_ratio: f64,
+    pub(crate) max_false_positive_rate: f64,
+    pub(crate) hook: Arc<dyn Fn(&SaturationEvent) + Send + Sync>,
+}
 
 /// Age-Partitioned Bloom Filter (APBF) described in Section 5
 /// in the original paper.
@@ -19,75 +117,444 @@ use crate::hash::{Hashers, Hashes};
 /// - `m`: number of bits for each slice.
 ///
 /// Therefore the backing bit array is of size `(k + l) * m` bits.
+/// Each slice starts at a 64-bit word boundary, so `m` is rounded up
+/// to a multiple of 64 in memory. The bit array is held in a [`BitStore`],
+/// which is a `Vec<u64>` by default.
+///
+/// The type of values may be unsized, such as `str` or `[u8]`, to insert
+/// borrowed values of any lifetime. They hash the same as references to them.
 #[derive(Clone)]
-pub struct APBF<T, H: Hashers> {
-    hashers: H,
-    bits: bv::BitVec, // underlying bit array
-    k: usize,         // number of slices to fill for each insertion
-    l: usize,         // number of slices in addition to k slices
-    m: usize,         // number of bits for each slice
-
-    n: u64,   // counter
-    p: usize, // position of the first logical slice on a bit vector
-    g: u64,   // generation
-    _t: PhantomData<T>,
+pub struct APBF<T: ?Sized, H: Hashers, S: BitStore = Vec<u64>> {
+    pub(crate) hashers: H,
+    pub(crate) bits: S,             // underlying bit array
+    pub(crate) k: usize,            // number of slices to fill for each insertion
+    pub(crate) l: usize,            // number of slices in addition to k slices
+    pub(crate) m: usize,            // number of bits for each slice
+    pub(crate) w: usize,            // number of words for each slice
+    pub(crate) w_log2: Option<u32>, // log2(w) if w is a power of two
+    pub(crate) layout: Layout,
+    pub(crate) clearing: Clearing,
+    pub(crate) s: usize,      // number of physical slices
+    pub(crate) ids: Vec<u64>, // index of hash function for each physical slice
+    pub(crate) next_id: u64,  // index of hash function for the next new slice
+    pub(crate) step: usize,   // number of words of the spare slice cleared on each insertion
+
+    pub(crate) n: u64,        // counter
+    pub(crate) p: usize,      // position of the first logical slice on a bit vector
+    pub(crate) g: u64,        // generation
+    pub(crate) cursor: usize, // next word to clear on the spare slice
+    pub(crate) shifts: u64,   // number of shifts so far
+
+    pub(crate) seed: Option<u64>, // seed of the hash functions if known
+    pub(crate) stats: Stats,
+    pub(crate) on_shift: Option<ShiftHook>,
+    pub(crate) on_saturation: Option<Saturation>,
+    pub(crate) _t: PhantomData<T>,
 }
 
-impl<T: Hash> APBF<T, KMHashers<RandomState, RandomState>> {
+impl<T: Hash + ?Sized> APBF<T, KMHashers<RandomState, RandomState>> {
     /// Creates a new APBF instance.
     pub fn new(k: usize, l: usize, m: usize) -> Self {
         Self::with_hashers(k, l, m, KMHashers::new(m as u64))
     }
+
+    /// Creates a new APBF instance which shifts the logical slices
+    /// every `g` insertions. See [`Builder::generation`] for its effect
+    /// on the false positive rate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `g` is zero.
+    pub fn with_generation(k: usize, l: usize, m: usize, g: u64) -> Self {
+        Builder::new(k, l, m).generation(g).build()
+    }
+
+    /// Returns a builder to configure a new APBF instance.
+    pub fn builder(
+        k: usize,
+        l: usize,
+        m: usize,
+    ) -> Builder<T, KMHashers<RandomState, RandomState>> {
+        Builder::new(k, l, m)
+    }
+
+    /// Creates a new APBF instance whose slice size `m` is a power of two.
+    ///
+    /// Slice offsets are computed with shifts and indices within a slice
+    /// are reduced with a bitmask instead of multiplications.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `m` is not a power of two.
+    pub fn new_pow2(k: usize, l: usize, m: usize) -> Self {
+        assert!(m.is_power_of_two(), "m must be a power of two, got {}", m);
+        Self::with_hashers(
+            k,
+            l,
+            m,
+            KMHashers::with_reduction(m as u64, Reduction::Mask),
+        )
+    }
+}
+
+impl<T: Hash + ?Sized> APBF<T, SplitHashers<SipBuildHasher128>> {
+    /// Creates a new APBF instance whose hash functions are derived from `seed`.
+    ///
+    /// It does not depend on the source of randomness used by [`APBF::new`],
+    /// which may be unavailable on some platforms such as
+    /// `wasm32-unknown-unknown`. Instances created with the same seed hash
+    /// values identically.
+    pub fn with_seed(k: usize, l: usize, m: usize, seed: u64) -> Self {
+        let mut apbf = Self::with_hashers(k, l, m, SplitHashers::with_seed(m as u64, seed));
+        apbf.seed = Some(seed);
+        apbf
+    }
 }
 
 impl<T, H> APBF<T, H>
 where
-    T: Hash,
+    T: Hash + ?Sized,
     H: Hashers,
 {
     pub fn with_hashers(k: usize, l: usize, m: usize, hashers: H) -> Self {
+        Builder::new(k, l, m).hashers(hashers).build()
+    }
+
+    pub(crate) fn from_builder(builder: Builder<T, H>) -> Self {
+        match Self::try_from_builder(builder, |_, len| Ok::<_, Infallible>(vec![0; len])) {
+            Ok(apbf) => apbf,
+            Err(e) => match e {},
+        }
+    }
+}
+
+impl<T, H, S> APBF<T, H, S>
+where
+    T: Hash + ?Sized,
+    H: Hashers,
+    S: BitStore,
+{
+    // Creates an APBF instance backed by the store returned by `alloc`
+    // given the number of physical slices and the number of words.
+    // The store must be filled with zeros.
+    pub(crate) fn try_from_builder<E, F>(builder: Builder<T, H>, alloc: F) -> Result<Self, E>
+    where
+        F: FnOnce(usize, usize) -> Result<S, E>,
+    {
+        let Builder {
+            config:
+                Config {
+                    k,
+                    l,
+                    m,
+                    layout,
+                    clearing,
+                    g,
+                    on_shift,
+                    on_saturation,
+                },
+            hashers,
+            ..
+        } = builder;
         debug_assert!(k > 0);
         debug_assert!(l > 0);
         debug_assert!(m > 0);
+        assert!(
+            layout != Layout::Blocked || m % WORD_BITS == 0,
+            "blocked layout requires m to be a multiple of {}, got {}",
+            WORD_BITS,
+            m
+        );
 
-        let g = ((m as f64) * std::f64::consts::LN_2 / (k as f64)) as u64;
-        let bits = bv::bitvec![0; (k + l) * m];
-        APBF {
+        assert!(g != Some(0), "generation must be positive");
+        let g = g.unwrap_or_else(|| analysis::generation(k, m));
+        let w = m.div_ceil(WORD_BITS);
+        let s = match clearing {
+            Clearing::Eager => k + l,
+            Clearing::Amortized => k + l + 1,
+        };
+        let bits = alloc(s, s * w)?;
+        Ok(APBF {
             hashers,
             n: 0,
             k,
             l,
             m,
+            w,
+            w_log2: if w.is_power_of_two() {
+                Some(w.trailing_zeros())
+            } else {
+                None
+            },
+            layout,
+            clearing,
+            s,
+            ids: (0..s as u64).collect(),
+            next_id: s as u64,
+            step: w.div_ceil(g.max(1) as usize),
             g,
             bits,
             p: 0,
+            cursor: w,
+            shifts: 0,
+            seed: None,
+            stats: Stats::default(),
+            on_shift,
+            on_saturation,
             _t: PhantomData,
-        }
+        })
     }
 
     fn shift(&mut self) {
-        let n_slices = self.k + self.l;
+        let retired_ones = match self.on_shift {
+            Some(_) => self.count_ones(self.physical(self.k + self.l - 1)),
+            None => 0,
+        };
 
-        let prev = self.p.checked_sub(1).unwrap_or(n_slices - 1);
-        let slice = self.get_slice_mut(prev);
-        slice.set_all(false);
+        let prev = self.p.checked_sub(1).unwrap_or(self.s - 1);
+        match self.clearing {
+            Clearing::Eager => self.clear_slice(prev),
+            // The spare slice has been mostly cleared during the last generation.
+            Clearing::Amortized => self.clear_words(prev, self.cursor, self.w),
+        }
 
-        self.p = if self.p == 0 {
-            self.l + self.k - 1
-        } else {
-            self.p - 1
-        };
+        let inserts = self.n;
+        self.p = prev;
         self.n = 0;
+        self.cursor = 0;
+        self.shifts += 1;
+        self.stats.record_shifts(1);
+        self.sync();
+
+        if let Some(f) = &self.on_shift {
+            f(&ShiftEvent {
+                generation: self.shifts,
+                inserts,
+                retired_ones,
+            });
+        }
+        self.check_saturation();
+    }
+
+    // Calls the function set by `set_on_saturation` if a threshold is exceeded.
+    fn check_saturation(&self) {
+        let saturation = match &self.on_saturation {
+            Some(s) => s,
+            None => return,
+        };
+        let fill_ratio = self.fill_ratio(self.k);
+        let fpr = self.estimated_false_positive_rate();
+        if fill_ratio > saturation.max_fill_ratio || fpr > saturation.max_false_positive_rate {
+            (saturation.hook)(&SaturationEvent {
+                generation: self.shifts,
+                fill_ratio,
+                estimated_false_positive_rate: fpr,
+                k: self.k,
+                l: self.l,
+                m: self.m,
+                g: self.g,
+            });
+        }
     }
 
-    fn get_slice(&self, i: usize) -> &bv::BitSlice {
-        let p = i * self.m;
-        &self.bits[p..p + self.m]
+    /// Shifts the logical slices, starting a new generation.
+    ///
+    /// The oldest slice is retired along with values not held by the others.
+    /// Insertions shift the slices every `g` insertions anyway. To age
+    /// the structure only with this method, e.g. from a timer, create it
+    /// with a generation of `u64::MAX`:
+    ///
+    /// ```
+    /// use apbf::APBF;
+    ///
+    /// let mut apbf = APBF::with_generation(10, 7, 1024, u64::MAX);
+    /// apbf.insert(42);
+    /// apbf.advance_by(10 + 7);
+    /// assert!(!apbf.contains(42));
+    /// ```
+    pub fn advance(&mut self) {
+        self.shift();
     }
 
-    fn get_slice_mut(&mut self, i: usize) -> &mut bv::BitSlice {
-        let p = i * self.m;
-        &mut self.bits[p..p + self.m]
+    /// Shifts the logical slices `n` times. See [`advance`](Self::advance).
+    ///
+    /// Since all slices are empty once each of them has been cleared,
+    /// further shifts only rotate the slices and do not invoke the hook set
+    /// by [`set_on_shift`](Self::set_on_shift).
+    pub fn advance_by(&mut self, n: u64) {
+        let s = self.s as u64;
+        for _ in 0..std::cmp::min(n, s) {
+            self.shift();
+        }
+        // Keep the slices where `n` shifts would have put them, so that
+        // the hash function of each logical slice stays the same.
+        let rest = n.saturating_sub(s);
+        self.p = (self.p + self.s - (rest % s) as usize) % self.s;
+        self.shifts += rest;
+        self.stats.record_shifts(rest);
+        if rest > 0 {
+            self.sync();
+        }
+    }
+
+    /// Sets a function called whenever the logical slices shift
+    /// and the oldest slice is retired, replacing the previous one.
+    ///
+    /// The function is called synchronously within the insertion
+    /// causing the shift, so it should return quickly.
+    pub fn set_on_shift<F>(&mut self, f: F)
+    where
+        F: Fn(&ShiftEvent) + Send + Sync + 'static,
+    {
+        self.on_shift = Some(Arc::new(f));
+    }
+
+    /// Sets a function called on a shift if the slice which has just stopped
+    /// receiving insertions is filled more than `max_fill_ratio`, or if
+    /// the [estimated false positive rate](Self::estimated_false_positive_rate)
+    /// exceeds `max_false_positive_rate`, replacing the previous one.
+    ///
+    /// It tells that the filter is undersized for the actual insertions,
+    /// e.g. by logging a warning. With the default generation, a slice is
+    /// half full when it stops receiving insertions. Checking costs time
+    /// proportional to `(k + l) * m` on each shift.
+    pub fn set_on_saturation<F>(&mut self, max_fill_ratio: f64, max_false_positive_rate: f64, f: F)
+    where
+        F: Fn(&SaturationEvent) + Send + Sync + 'static,
+    {
+        self.on_saturation = Some(Saturation {
+            max_fill_ratio,
+            max_false_positive_rate,
+            hook: Arc::new(f),
+        });
+    }
+
+    // Clears some words of the spare slice.
+    fn clear_spare(&mut self) {
+        if self.cursor < self.w {
+            let spare = self.p.checked_sub(1).unwrap_or(self.s - 1);
+            let end = std::cmp::min(self.cursor + self.step, self.w);
+            self.clear_words(spare, self.cursor, end);
+            self.cursor = end;
+        }
+    }
+
+    // Returns the position of the i-th logical slice on the bits.
+    #[inline]
+    pub(crate) fn physical(&self, i: usize) -> usize {
+        let pos = self.p + i;
+        pos.checked_sub(self.s).unwrap_or(pos)
+    }
+
+    // Returns the word offset of the i-th physical slice.
+    #[inline]
+    pub(crate) fn slice_offset(&self, i: usize) -> usize {
+        match self.w_log2 {
+            Some(s) => i << s,
+            None => i * self.w,
+        }
+    }
+
+    // Returns the word offset of the block which a value belongs to.
+    // It is always zero for the sliced layout.
+    #[inline]
+    pub(crate) fn block_offset(&self, hashes: &H::H) -> usize {
+        match self.layout {
+            Layout::Sliced => 0,
+            Layout::Blocked => {
+                let b = hashes.get(BLOCK_HASH) as usize / WORD_BITS;
+                b * self.s
+            }
+        }
+    }
+
+    // Returns the index of a word and a mask to access the h-th bit
+    // of the i-th physical slice.
+    #[inline]
+    fn locate(&self, block: usize, i: usize, h: usize) -> (usize, u64) {
+        debug_assert!(h < self.m);
+        let p = match self.layout {
+            Layout::Sliced => self.slice_offset(i) + h / WORD_BITS,
+            Layout::Blocked => block + i,
+        };
+        (p, 1 << (h % WORD_BITS))
+    }
+
+    #[inline]
+    fn set_bit(&mut self, block: usize, i: usize, h: usize) {
+        let (p, mask) = self.locate(block, i, h);
+        self.bits.words_mut()[p] |= mask;
+    }
+
+    #[inline]
+    fn get_bit(&self, block: usize, i: usize, h: usize) -> bool {
+        let (p, mask) = self.locate(block, i, h);
+        self.bits.words()[p] & mask != 0
+    }
+
+    // Hints the processor to load the words holding the bits of a value on
+    // the logical slices in `range`, so that the cache misses overlap.
+    // It is a no-op without the `prefetch` feature, on other architectures
+    // than x86 and x86-64, and for the blocked layout, whose words lie within
+    // a single block anyway.
+    //
+    // Insertions into a sliced filter much larger than the cache took about
+    // 20% less time with k = 14 in our measurements. Queries are not
+    // prefetched, since most of the words would be skipped by the search.
+    #[inline]
+    fn prefetch(&self, hashes: &H::H, block: usize, range: Range<usize>) {
+        #[cfg(all(feature = "prefetch", any(target_arch = "x86", target_arch = "x86_64")))]
+        {
+            #[cfg(target_arch = "x86")]
+            use std::arch::x86::{_mm_prefetch, _MM_HINT_T0};
+            #[cfg(target_arch = "x86_64")]
+            use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+
+            if self.layout == Layout::Sliced {
+                let words = self.bits.words().as_ptr();
+                for i in range {
+                    let pos = self.physical(i);
+                    let h = hashes.get(self.ids[pos]) as usize;
+                    let p = self.locate(block, pos, h).0;
+                    unsafe { _mm_prefetch::<_MM_HINT_T0>(words.add(p) as *const i8) };
+                }
+            }
+        }
+        #[cfg(not(all(feature = "prefetch", any(target_arch = "x86", target_arch = "x86_64"))))]
+        let _ = (hashes, block, range);
+    }
+
+    // Returns an iterator over word indices of the i-th physical slice.
+    fn slice_words(&self, i: usize) -> impl Iterator<Item = usize> {
+        slice_words(self.layout, self.slice_offset(i), i, self.s, self.w)
+    }
+
+    fn clear_slice(&mut self, i: usize) {
+        self.clear_words(i, 0, self.w);
+    }
+
+    // Clears words in range `[from, to)` of the i-th physical slice.
+    fn clear_words(&mut self, i: usize, from: usize, to: usize) {
+        match self.layout {
+            Layout::Sliced => {
+                let p = self.slice_offset(i);
+                for word in &mut self.bits.words_mut()[p + from..p + to] {
+                    *word = 0;
+                }
+            }
+            Layout::Blocked => {
+                for p in self.slice_words(i).skip(from).take(to - from) {
+                    self.bits.words_mut()[p] = 0;
+                }
+            }
+        }
+    }
+
+    // Returns the number of bits set in the i-th physical slice.
+    fn count_ones(&self, i: usize) -> usize {
+        self.slice_words(i)
+            .map(|p| self.bits.words()[p].count_ones() as usize)
+            .sum()
     }
 
     /// Inserts a value to the structure.
@@ -95,24 +562,60 @@ where
     where
         V: Borrow<T>,
     {
-        let n_slices = self.k + self.l;
+        let hashes = self.hashers.hash(value);
+        self.insert_hashed(&hashes);
+    }
+
+    // Inserts a value given its hash values.
+    pub(crate) fn insert_hashed(&mut self, hashes: &H::H) {
+        if self.n >= self.g {
+            self.shift();
+        }
+
+        let block = self.block_offset(hashes);
+        self.insert_hashes(hashes, block);
+    }
 
+    /// Inserts a value unless the structure already holds it, and returns
+    /// `true` if it was inserted.
+    ///
+    /// Unlike [`insert`](Self::insert), values found in the structure do not
+    /// count toward the generation, so duplicates do not push the others out
+    /// of the window. A value is not refreshed either, and is forgotten once
+    /// the slices holding it are retired, however often it is seen. A false
+    /// positive makes a new value be reported as not inserted.
+    pub fn insert_if_absent<V>(&mut self, value: V) -> bool
+    where
+        V: Borrow<T>,
+    {
         if self.n >= self.g {
             self.shift();
         }
 
         let hashes = self.hashers.hash(value);
-        for i in 0..self.k {
-            // Compute position of the i-th logical slice on the bits.
-            let pos = self.p + i;
-            let pos = pos.checked_sub(n_slices).unwrap_or(pos);
+        let block = self.block_offset(&hashes);
+        if search(self.k, self.l, |i| self.hit(&hashes, block, i)) {
+            return false;
+        }
+        self.insert_hashes(&hashes, block);
+        true
+    }
 
-            let slice = self.get_slice_mut(pos);
-            let h = hashes.get(pos as u64) as usize;
-            slice.set(h, true);
+    // Sets the bits of a value on the first k logical slices.
+    #[inline]
+    pub(crate) fn insert_hashes(&mut self, hashes: &H::H, block: usize) {
+        self.prefetch(hashes, block, 0..self.k);
+        for i in 0..self.k {
+            let pos = self.physical(i);
+            let h = hashes.get(self.ids[pos]) as usize;
+            self.set_bit(block, pos, h);
         }
 
+        if self.clearing == Clearing::Amortized {
+            self.clear_spare();
+        }
         self.n += 1;
+        self.stats.record_insert();
     }
 
     /// Returns `true` if the structure holds a given value.
@@ -120,34 +623,308 @@ where
     where
         V: Borrow<T>,
     {
-        let n_slices = self.k + self.l;
-        let mut i = self.l;
-        let mut prev_count = 0;
-        let mut count = 0;
+        let hashes = self.hashers.hash(value);
+        self.contains_hashed(&hashes)
+    }
+
+    // Returns `true` if the structure holds a value given its hash values.
+    pub(crate) fn contains_hashed(&self, hashes: &H::H) -> bool {
+        self.contains_after_shifts(hashes, 0)
+    }
+
+    // Returns `true` if the structure would hold a value after `shifts` more
+    // shifts. The shifts push the newest slices out of the search range
+    // with empty ones, which cannot be part of a run.
+    pub(crate) fn contains_after_shifts(&self, hashes: &H::H, shifts: u64) -> bool {
+        let hit = match (self.l as u64).checked_sub(shifts) {
+            Some(l) => {
+                let block = self.block_offset(hashes);
+                search(self.k, l as usize, |i| self.hit(hashes, block, i))
+            }
+            None => false,
+        };
+        self.stats.record_query(hit);
+        hit
+    }
 
+    /// Returns the length of the longest run of consecutive logical slices
+    /// having the bits of a given value set.
+    ///
+    /// [`contains`](Self::contains) is equivalent to checking if it is at least `k`.
+    /// Callers may apply a different threshold, e.g. a value inserted recently
+    /// tends to have a longer run, up to `k + l` if it has been inserted
+    /// repeatedly in every generation.
+    pub fn contains_count<V>(&self, value: V) -> usize
+    where
+        V: Borrow<T>,
+    {
         let hashes = self.hashers.hash(value);
-        loop {
-            let pos = self.p + i;
-            let pos = pos.checked_sub(n_slices).unwrap_or(pos);
-
-            let slice = self.get_slice(pos);
-            let h = hashes.get(pos as u64) as usize;
-            let hit = *slice.get(h).unwrap();
-            if hit {
+        let block = self.block_offset(&hashes);
+
+        let mut longest = 0;
+        let mut count = 0;
+        for i in 0..self.k + self.l {
+            if self.hit(&hashes, block, i) {
                 count += 1;
-                i += 1;
-                if prev_count + count == self.k {
-                    return true;
-                }
+                longest = std::cmp::max(longest, count);
             } else {
-                if i < self.k {
-                    return false;
-                }
-                i -= self.k;
-                prev_count = count;
                 count = 0;
             }
         }
+        longest
+    }
+
+    /// Returns the estimated age of a given value in generations,
+    /// i.e. how many times the logical slices have shifted since it was
+    /// inserted, or `None` if the structure does not hold the value.
+    ///
+    /// A value inserted in the current generation has age zero.
+    /// With the default aging driven by insertions, a value of age `a > 0`
+    /// was inserted between `n + (a - 1) * g` and `n + a * g - 1` insertions
+    /// ago, where `n` is the number of insertions in the current generation.
+    ///
+    /// The age is found from the newest run of at least `k` consecutive slices
+    /// having the bits of the value set. Since the slices next to the run may
+    /// have the bits set by other values, a run longer than `k` is consistent
+    /// with several ages, so a range is returned.
+    pub fn age_of<V>(&self, value: V) -> Option<RangeInclusive<u64>>
+    where
+        V: Borrow<T>,
+    {
+        let hashes = self.hashers.hash(value);
+        let block = self.block_offset(&hashes);
+
+        let n_slices = self.k + self.l;
+        let mut start = 0;
+        for i in 0..=n_slices {
+            if i < n_slices && self.hit(&hashes, block, i) {
+                continue;
+            }
+            if i - start >= self.k {
+                return Some(start as u64..=(i - self.k) as u64);
+            }
+            start = i + 1;
+        }
+        None
+    }
+
+    // Returns `true` if the bit of a value is set on the i-th logical slice.
+    #[inline]
+    fn hit(&self, hashes: &H::H, block: usize, i: usize) -> bool {
+        let pos = self.physical(i);
+        let h = hashes.get(self.ids[pos]) as usize;
+        self.get_bit(block, pos, h)
+    }
+
+    /// Removes all values from the structure, keeping its parameters
+    /// and hash functions.
+    pub fn clear(&mut self) {
+        for word in self.bits.words_mut() {
+            *word = 0;
+        }
+        self.n = 0;
+        self.p = 0;
+        self.cursor = self.w;
+        self.shifts = 0;
+        self.sync();
+    }
+
+    /// Adds the values held by `other` into this structure, as if they
+    /// had been inserted into it in the same generations.
+    ///
+    /// Both must have the same parameters, layout, clearing strategy and hash
+    /// functions, and their logical slices must line up, which is the case
+    /// when they have shifted the same number of times. Returns `false`
+    /// without modifying the structure otherwise. Whether the hash functions
+    /// are the same cannot be checked.
+    pub fn merge<S2: BitStore>(&mut self, other: &APBF<T, H, S2>) -> bool {
+        let compatible = (self.k, self.l, self.m, self.layout, self.clearing)
+            == (other.k, other.l, other.m, other.layout, other.clearing)
+            && (0..self.k + self.l)
+                .all(|i| self.ids[self.physical(i)] == other.ids[other.physical(i)]);
+        if !compatible {
+            return false;
+        }
+
+        for i in 0..self.k + self.l {
+            let dst = self.slice_words(self.physical(i));
+            let src = other.slice_words(other.physical(i));
+            for (p, q) in dst.zip(src) {
+                self.bits.words_mut()[p] |= other.bits.words()[q];
+            }
+        }
+        self.n = std::cmp::max(self.n, other.n);
+        true
+    }
+
+    /// Removes all values from the structure and replaces the hash functions
+    /// with ones derived from `seed`.
+    ///
+    /// The seed is only recorded in the serialized state if it is enough
+    /// to rebuild the hash functions, as told by
+    /// [`SeedableHashers::RESTORABLE`].
+    pub fn reset_with_seed(&mut self, seed: u64)
+    where
+        H: SeedableHashers,
+    {
+        self.clear();
+        self.hashers.reseed(seed);
+        self.seed = if H::RESTORABLE { Some(seed) } else { None };
+        self.sync();
+    }
+
+    /// Changes the number of slices `l` besides the `k` slices,
+    /// which changes the window to `new_l * g` insertions.
+    ///
+    /// Values are kept in the same logical slices. When growing, empty slices
+    /// are added as the oldest ones. When shrinking, the oldest slices are
+    /// dropped along with the values only held by them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `new_l` is zero, or the [`BitStore`] cannot change its length.
+    pub fn resize_window(&mut self, new_l: usize) {
+        assert!(new_l > 0, "l must be positive");
+
+        let s = self.s - self.l + new_l;
+        let slice_offset = |i: usize| match self.w_log2 {
+            Some(sh) => i << sh,
+            None => i * self.w,
+        };
+        let mut bits = vec![0; s * self.w];
+        let mut ids = Vec::with_capacity(s);
+        for i in 0..std::cmp::min(self.k + self.l, self.k + new_l) {
+            let pos = self.physical(i);
+            let src = self.slice_words(pos);
+            let dst = slice_words(self.layout, slice_offset(i), i, s, self.w);
+            for (p, q) in src.zip(dst) {
+                bits[q] = self.bits.words()[p];
+            }
+            ids.push(self.ids[pos]);
+        }
+        let mut next_id = self.next_id;
+        while ids.len() < s {
+            ids.push(next_id);
+            next_id += 1;
+        }
+
+        assert!(
+            self.bits.replace(bits),
+            "the store cannot change its length"
+        );
+        self.next_id = next_id;
+        self.ids = ids;
+        self.l = new_l;
+        self.s = s;
+        self.p = 0;
+        // The spare slice, if any, is allocated clean.
+        self.cursor = self.w;
+        self.sync();
+    }
+
+    /// Returns an estimate of the number of distinct values currently
+    /// retained in the structure.
+    ///
+    /// Each slice gets one bit per insertion, so the number of values inserted
+    /// into a slice is estimated from its fill ratio as `-m * ln(1 - ones / m)`.
+    /// Since every value is inserted into `k` slices, the sum over all slices
+    /// is divided by `k`. Values in the transition zone whose older slices
+    /// have been retired are counted fractionally.
+    ///
+    /// Returns infinity if some slice is saturated, i.e. all of its bits are set.
+    pub fn estimate_len(&self) -> f64 {
+        let m = self.m as f64;
+        let sum = (0..self.k + self.l)
+            .map(|i| {
+                let ones = self.count_ones(self.physical(i)) as f64;
+                -m * (1.0 - ones / m).ln()
+            })
+            .sum::<f64>();
+        sum / self.k as f64
+    }
+
+    /// Returns the ratio of bits set in the i-th logical slice,
+    /// where the 0-th slice is the one receiving the latest insertions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is not less than `k + l`.
+    pub fn fill_ratio(&self, i: usize) -> f64 {
+        assert!(i < self.k + self.l, "slice index out of range: {}", i);
+        self.count_ones(self.physical(i)) as f64 / self.m as f64
+    }
+
+    /// Returns an estimate of the current false positive rate.
+    ///
+    /// A query reports a false positive when it finds `k` consecutive slices
+    /// with the bit set. Regarding each bit as set independently with
+    /// probability equal to the fill ratio of its slice, this computes
+    /// the probability that such a run exists.
+    pub fn estimated_false_positive_rate(&self) -> f64 {
+        let ratios = (0..self.k + self.l).map(|i| self.fill_ratio(i));
+        run_probability(self.k, ratios)
+    }
+
+    /// Returns the number of slices filled for each insertion.
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Returns the number of slices besides the `k` slices.
+    pub fn l(&self) -> usize {
+        self.l
+    }
+
+    /// Returns the number of bits for each slice.
+    pub fn m(&self) -> usize {
+        self.m
+    }
+
+    /// Returns the number of bytes taken by the structure, including the bit
+    /// array wherever it is stored and the fields of the structure itself,
+    /// but not the memory owned by the hash functions or the hook.
+    ///
+    /// See [`analysis::estimated_memory`] to compute it from parameters.
+    pub fn memory_usage(&self) -> usize {
+        mem::size_of::<Self>()
+            + mem::size_of_val(self.bits.words())
+            + self.ids.capacity() * mem::size_of::<u64>()
+    }
+
+    /// Returns the number of insertions since the structure was created,
+    /// which [`clear`](Self::clear) does not reset.
+    #[cfg(feature = "stats")]
+    pub fn total_inserts(&self) -> u64 {
+        self.stats.inserts()
+    }
+
+    /// Returns the number of shifts since the structure was created,
+    /// which [`clear`](Self::clear) does not reset.
+    #[cfg(feature = "stats")]
+    pub fn total_shifts(&self) -> u64 {
+        self.stats.shifts()
+    }
+
+    /// Returns the number of calls to [`contains`](Self::contains)
+    /// since the structure was created.
+    #[cfg(feature = "stats")]
+    pub fn queries(&self) -> u64 {
+        self.stats.queries()
+    }
+
+    /// Returns the number of calls to [`contains`](Self::contains)
+    /// which returned `true`. Comparing its ratio to [`queries`](Self::queries)
+    /// and the insertion rate against the sizing assumptions tells whether
+    /// the filter still fits the workload.
+    #[cfg(feature = "stats")]
+    pub fn query_hits(&self) -> u64 {
+        self.stats.hits()
+    }
+
+    /// Returns the number of insertions in the current generation.
+    /// The logical slices shift when it reaches the generation.
+    pub fn inserts_since_shift(&self) -> u64 {
+        self.n
     }
 
     // Returns width of the sliding window, where inserted values
@@ -168,6 +945,88 @@ where
     }
 }
 
+// Shows the parameters, the state and the number of bits set
+// in each logical slice from the newest.
+impl<T, H, S> fmt::Debug for APBF<T, H, S>
+where
+    T: Hash + ?Sized,
+    H: Hashers,
+    S: BitStore,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ones = (0..self.k + self.l)
+            .map(|i| self.count_ones(self.physical(i)))
+            .collect::<Vec<_>>();
+        f.debug_struct("APBF")
+            .field("k", &self.k)
+            .field("l", &self.l)
+            .field("m", &self.m)
+            .field("g", &self.g)
+            .field("layout", &self.layout)
+            .field("clearing", &self.clearing)
+            .field("n", &self.n)
+            .field("p", &self.p)
+            .field("shifts", &self.shifts)
+            .field("ones", &ones)
+            .finish()
+    }
+}
+
+impl<T: ?Sized, H: Hashers, S: BitStore> Drop for APBF<T, H, S> {
+    fn drop(&mut self) {
+        self.sync();
+    }
+}
+
+// Returns an iterator over word indices of a physical slice at `offset`
+// or the i-th position, depending on the layout.
+fn slice_words(
+    layout: Layout,
+    offset: usize,
+    i: usize,
+    s: usize,
+    w: usize,
+) -> impl Iterator<Item = usize> {
+    let (start, step) = match layout {
+        Layout::Sliced => (offset, 1),
+        Layout::Blocked => (i, s),
+    };
+    (0..w).map(move |j| start + j * step)
+}
+
+// Returns `true` if `hit` holds for `k` consecutive logical slices among `k + l`.
+// Windows of `k` slices are tested from the one ending at the oldest slice
+// toward the newest, carrying over hits which continue into the next window.
+//
+// Probing all slices at once into a bit mask and testing it for a run of `k`
+// ones without branches was measured to be 1.3x to 3x slower, even for
+// values held by the structure: each probe computes a hash value and loads
+// a word, which dominate the cost, and the search skips most of them.
+pub(crate) fn search<F>(k: usize, l: usize, hit: F) -> bool
+where
+    F: Fn(usize) -> bool,
+{
+    let mut i = l;
+    let mut prev_count = 0;
+    let mut count = 0;
+    loop {
+        if hit(i) {
+            count += 1;
+            i += 1;
+            if prev_count + count == k {
+                return true;
+            }
+        } else {
+            if i < k {
+                return false;
+            }
+            i -= k;
+            prev_count = count;
+            count = 0;
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -184,13 +1043,11 @@ mod tests {
         apbf.insert(value);
 
         for i in 0..k {
-            let slice = apbf.get_slice(i);
-            assert_eq!(slice.count_ones(), 1);
+            assert_eq!(apbf.count_ones(i), 1);
         }
 
         for i in k..(k + l) {
-            let slice = apbf.get_slice(i);
-            assert_eq!(slice.count_ones(), 0);
+            assert_eq!(apbf.count_ones(i), 0);
         }
     }
 
@@ -211,6 +1068,604 @@ mod tests {
         assert_eq!(apbf.p, apbf.k + apbf.l - 1);
     }
 
+    #[test]
+    fn test_shift_clears_slice() {
+        let k = 3;
+        let l = 2;
+        let m = 100;
+        let mut apbf = APBF::new(k, l, m);
+
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        for _ in 0..(apbf.g * (k + l) as u64) {
+            apbf.insert(rng.gen::<u64>());
+        }
+        let prev = apbf.p.checked_sub(1).unwrap_or(k + l - 1);
+        assert!(apbf.count_ones(prev) > 0);
+
+        apbf.shift();
+        assert_eq!(apbf.count_ones(apbf.p), 0);
+    }
+
+    #[test]
+    fn test_new_pow2() {
+        let mut apbf = APBF::new_pow2(10, 20, 256);
+        assert_eq!(apbf.w_log2, Some(2));
+
+        let value = 42usize;
+        apbf.insert(value);
+        for i in 0..10 {
+            assert_eq!(apbf.count_ones(i), 1);
+        }
+        assert!(apbf.contains(value));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_pow2_rejects_non_power_of_two() {
+        APBF::<usize, _>::new_pow2(10, 20, 100);
+    }
+
+    #[test]
+    fn test_blocked() {
+        let k = 10;
+        let l = 20;
+        let m = 256;
+        let mut apbf = APBF::builder(k, l, m).layout(Layout::Blocked).build();
+
+        let value = 42usize;
+        apbf.insert(value);
+        for i in 0..k {
+            assert_eq!(apbf.count_ones(i), 1);
+        }
+        for i in k..(k + l) {
+            assert_eq!(apbf.count_ones(i), 0);
+        }
+        assert!(apbf.contains(value));
+
+        // All bits of the value are placed in the same block.
+        let words = apbf.bits.iter().enumerate().filter(|(_, w)| **w != 0);
+        let blocks = words.map(|(p, _)| p / (k + l)).collect::<Vec<_>>();
+        assert_eq!(blocks.len(), k);
+        assert!(blocks.iter().all(|&b| b == blocks[0]));
+    }
+
+    #[test]
+    fn test_blocked_contains_in_window() {
+        // Seeded so that the value does not turn into a false positive.
+        let mut apbf = APBF::builder(10, 20, 64)
+            .hashers(SplitHashers::with_seed(64, 0))
+            .layout(Layout::Blocked)
+            .build();
+        let value = 42usize;
+
+        apbf.insert(value);
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        let w = apbf.window();
+        let s = apbf.slack();
+        for _ in 0..w {
+            apbf.insert(rng.gen::<usize>());
+            assert!(apbf.contains(value));
+        }
+        for _ in 0..s {
+            apbf.insert(rng.gen::<usize>());
+        }
+        assert!(!apbf.contains(value));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_blocked_rejects_unaligned_slices() {
+        APBF::<usize, _>::builder(10, 20, 100)
+            .layout(Layout::Blocked)
+            .build();
+    }
+
+    #[test]
+    fn test_amortized_clearing() {
+        let k = 3;
+        let l = 2;
+        let m = 1024;
+        let mut apbf = APBF::builder(k, l, m).clearing(Clearing::Amortized).build();
+        assert_eq!(apbf.s, k + l + 1);
+
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        for _ in 0..(apbf.g * (k + l + 1) as u64) {
+            apbf.insert(rng.gen::<u64>());
+        }
+        let spare = apbf.p.checked_sub(1).unwrap_or(apbf.s - 1);
+        assert_eq!(apbf.count_ones(spare), 0);
+
+        // The slice retired by the next shift becomes the spare,
+        // which is cleared within the following generation.
+        apbf.insert(rng.gen::<u64>());
+        let spare = apbf.p.checked_sub(1).unwrap_or(apbf.s - 1);
+        assert!(apbf.count_ones(spare) > 0);
+        for _ in 1..apbf.g {
+            apbf.insert(rng.gen::<u64>());
+        }
+        assert_eq!(apbf.count_ones(spare), 0);
+    }
+
+    #[test]
+    fn test_amortized_contains_in_window() {
+        for &layout in &[Layout::Sliced, Layout::Blocked] {
+            // Seeded so that the value does not turn into a false positive.
+            let mut apbf = APBF::builder(10, 20, 64)
+                .hashers(SplitHashers::with_seed(64, 0))
+                .layout(layout)
+                .clearing(Clearing::Amortized)
+                .build();
+            let value = 42usize;
+
+            apbf.insert(value);
+            let mut rng = StdRng::from_seed([0u8; 32]);
+            let w = apbf.window();
+            let s = apbf.slack();
+            for _ in 0..w {
+                apbf.insert(rng.gen::<usize>());
+                assert!(apbf.contains(value));
+            }
+            for _ in 0..s {
+                apbf.insert(rng.gen::<usize>());
+            }
+            assert!(!apbf.contains(value));
+        }
+    }
+
+    #[cfg(feature = "wyhash")]
+    #[test]
+    fn test_seeded_hashers() {
+        use crate::hash::wy::WyBuildHasher;
+
+        let build = || {
+            APBF::builder(10, 20, 256)
+                .build_hashers(WyBuildHasher::with_seed(1), WyBuildHasher::with_seed(2))
+                .build()
+        };
+        let mut apbf1 = build();
+        let mut apbf2 = build();
+        for i in 0..1000usize {
+            apbf1.insert(i);
+            apbf2.insert(i);
+        }
+        assert_eq!(apbf1.bits, apbf2.bits);
+    }
+
+    #[test]
+    fn test_split_hashers() {
+        use crate::hash::split::SplitHashers;
+
+        let m = 64;
+        let mut apbf: APBF<String, _> = APBF::builder(10, 20, m)
+            .hashers(SplitHashers::new(m as u64))
+            .build();
+        let value = "apbf".to_string();
+
+        apbf.insert(&value);
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        for _ in 0..apbf.window() {
+            apbf.insert(rng.gen::<u64>().to_string());
+            assert!(apbf.contains(&value));
+        }
+    }
+
+    #[test]
+    fn test_estimate_len() {
+        let mut apbf = APBF::new(10, 20, 1024);
+        assert_eq!(apbf.estimate_len(), 0.0);
+
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        for _ in 0..500 {
+            apbf.insert(rng.gen::<u64>());
+        }
+        let len = apbf.estimate_len();
+        assert!((450.0..550.0).contains(&len), "estimated {}", len);
+
+        // Values older than the window and the transition zone are not counted.
+        for _ in 0..(apbf.window() + apbf.slack()) {
+            apbf.insert(rng.gen::<u64>());
+        }
+        let len = apbf.estimate_len();
+        let max = (apbf.window() + apbf.slack()) as f64;
+        assert!(len < max * 1.1, "estimated {}", len);
+    }
+
+    #[test]
+    fn test_estimated_false_positive_rate() {
+        let k = 4;
+        let l = 3;
+        let mut apbf = APBF::new(k, l, 1024);
+        assert_eq!(apbf.estimated_false_positive_rate(), 0.0);
+        for i in 0..(k + l) {
+            assert_eq!(apbf.fill_ratio(i), 0.0);
+        }
+
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        for _ in 0..(apbf.window() + apbf.slack()) {
+            apbf.insert(rng.gen::<u64>());
+        }
+        assert!(apbf.fill_ratio(0) > 0.0);
+        assert!(apbf.fill_ratio(k + l - 1) > 0.4);
+
+        let queries = 100_000;
+        let fp = (0..queries)
+            .filter(|_| apbf.contains(rng.gen::<u64>()))
+            .count();
+        let measured = fp as f64 / queries as f64;
+        let estimated = apbf.estimated_false_positive_rate();
+        assert!(
+            (estimated / 1.5..estimated * 1.5).contains(&measured),
+            "estimated {}, measured {}",
+            estimated,
+            measured
+        );
+    }
+
+    #[test]
+    fn test_false_positive_rate_tracks_theory() {
+        let k = 4;
+        let l = 3;
+        let m = 4096;
+        let mut apbf = APBF::new(k, l, m);
+
+        // Fill the structure up to the end of a generation,
+        // where the false positive rate is maximal.
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        for _ in 0..(apbf.g * (k + l) as u64) {
+            apbf.insert(rng.gen::<u64>());
+        }
+
+        let queries = 100_000;
+        let fp = (0..queries)
+            .filter(|_| apbf.contains(rng.gen::<u64>()))
+            .count();
+        let measured = fp as f64 / queries as f64;
+        let expected = analysis::false_positive_rate(k, l, m);
+        assert!(
+            (expected / 1.5..expected * 1.5).contains(&measured),
+            "expected {}, measured {}",
+            expected,
+            measured
+        );
+    }
+
+    #[test]
+    fn test_on_shift() {
+        use std::sync::Mutex;
+
+        let k = 3;
+        let l = 2;
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut apbf = {
+            let events = events.clone();
+            APBF::builder(k, l, 1024)
+                .on_shift(move |e| events.lock().unwrap().push(e.clone()))
+                .build()
+        };
+
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        for _ in 0..(apbf.g * (k + l + 1) as u64) {
+            apbf.insert(rng.gen::<u64>());
+        }
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), k + l);
+        for (i, e) in events.iter().enumerate() {
+            assert_eq!(e.generation, i as u64 + 1);
+            assert_eq!(e.inserts, apbf.g);
+        }
+        // The first l shifts retire slices which have never been filled.
+        assert_eq!(events[l - 1].retired_ones, 0);
+        assert!(events[l].retired_ones > 0);
+    }
+
+    #[test]
+    fn test_on_saturation() {
+        use std::sync::Mutex;
+
+        let (k, l, m) = (3, 2, 1024);
+        let default_g = analysis::generation(k, m);
+        for &(g, alerts) in &[(default_g, false), (default_g * 2, true)] {
+            let events = Arc::new(Mutex::new(Vec::new()));
+            let mut apbf = {
+                let events = events.clone();
+                APBF::builder(k, l, m)
+                    .generation(g)
+                    .on_saturation(0.6, 1.0, move |e| events.lock().unwrap().push(e.clone()))
+                    .build()
+            };
+
+            let mut rng = StdRng::from_seed([0u8; 32]);
+            for _ in 0..(g * (k + l + 1) as u64) {
+                apbf.insert(rng.gen::<u64>());
+            }
+
+            let events = events.lock().unwrap();
+            assert_eq!(!events.is_empty(), alerts);
+            for e in events.iter() {
+                assert!(e.fill_ratio > 0.6);
+                assert_eq!((e.k, e.l, e.m, e.g), (k, l, m, g));
+            }
+        }
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut apbf = APBF::new(10, 20, 64);
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        for _ in 0..1000 {
+            apbf.insert(rng.gen::<u64>());
+        }
+
+        apbf.clear();
+        assert!(apbf.bits.iter().all(|&w| w == 0));
+        assert_eq!(apbf.p, 0);
+        assert_eq!(apbf.n, 0);
+
+        let value = 42u64;
+        apbf.insert(value);
+        assert!(apbf.contains(value));
+    }
+
+    #[test]
+    fn test_merge() {
+        for &layout in &[Layout::Sliced, Layout::Blocked] {
+            for &clearing in &[Clearing::Eager, Clearing::Amortized] {
+                let hashers = KMHashers::new(1024);
+                let build = || -> APBF<u64, _> {
+                    Builder::new(4, 3, 1024)
+                        .hashers(hashers.clone())
+                        .layout(layout)
+                        .clearing(clearing)
+                        .generation(100)
+                        .build()
+                };
+                let mut apbf1 = build();
+                let mut apbf2 = build();
+                for v in 0..300 {
+                    apbf1.insert(v);
+                    apbf2.insert(v + 300);
+                }
+                assert!(apbf1.merge(&apbf2));
+                for v in 0..600 {
+                    assert!(apbf1.contains(v));
+                }
+
+                apbf2.insert(0);
+                apbf2.advance();
+                assert!(!apbf1.merge(&apbf2));
+            }
+        }
+    }
+
+    #[test]
+    fn test_reset_with_seed() {
+        use crate::hash::split::SplitHashers;
+
+        let m = 256;
+        let build = || -> APBF<usize, _> {
+            APBF::builder(10, 20, m)
+                .hashers(SplitHashers::new(m as u64))
+                .build()
+        };
+        let mut apbf1 = build();
+        let mut apbf2 = build();
+        apbf1.insert(1);
+        apbf1.reset_with_seed(42);
+        apbf2.reset_with_seed(42);
+        for i in 0..1000 {
+            apbf1.insert(i);
+            apbf2.insert(i);
+        }
+        assert_eq!(apbf1.bits, apbf2.bits);
+    }
+
+    #[test]
+    fn test_resize_window() {
+        for &layout in &[Layout::Sliced, Layout::Blocked] {
+            for &clearing in &[Clearing::Eager, Clearing::Amortized] {
+                // Seeded so that the value is not a false positive after shrinking.
+                let mut apbf = APBF::builder(10, 20, 64)
+                    .hashers(SplitHashers::with_seed(64, 0))
+                    .layout(layout)
+                    .clearing(clearing)
+                    .build();
+                let mut rng = StdRng::from_seed([0u8; 32]);
+
+                let value = 42usize;
+                apbf.insert(value);
+                for _ in 0..apbf.window() / 2 {
+                    apbf.insert(rng.gen::<usize>());
+                }
+
+                // Grow the window and fill the remaining part of it.
+                let w = apbf.window();
+                apbf.resize_window(40);
+                assert_eq!(apbf.window(), 2 * w);
+                for _ in 0..(apbf.window() - w) {
+                    apbf.insert(rng.gen::<usize>());
+                    assert!(apbf.contains(value));
+                }
+
+                // Shrink the window so that the value is dropped.
+                apbf.resize_window(5);
+                assert!(!apbf.contains(value));
+
+                let value = 43usize;
+                apbf.insert(value);
+                for _ in 0..apbf.window() {
+                    apbf.insert(rng.gen::<usize>());
+                    assert!(apbf.contains(value));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_with_generation() {
+        let apbf = APBF::<usize, _>::with_generation(10, 20, 64, 3);
+        assert_eq!(apbf.generation(), 3);
+        assert_eq!(apbf.window(), 60);
+
+        // Seeded so that the value does not turn into a false positive.
+        let mut apbf = APBF::builder(10, 20, 64)
+            .hashers(SplitHashers::with_seed(64, 0))
+            .generation(3)
+            .build();
+
+        let value = 42usize;
+        apbf.insert(value);
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        for _ in 0..apbf.window() {
+            apbf.insert(rng.gen::<usize>());
+            assert!(apbf.contains(value));
+        }
+        for _ in 0..apbf.slack() {
+            apbf.insert(rng.gen::<usize>());
+        }
+        assert!(!apbf.contains(value));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_with_generation_rejects_zero() {
+        APBF::<usize, _>::with_generation(10, 20, 64, 0);
+    }
+
+    #[test]
+    fn test_contains_count() {
+        let k = 10;
+        let mut apbf = APBF::new(k, 20, 64);
+        let value = 42usize;
+        assert_eq!(apbf.contains_count(value), 0);
+
+        apbf.insert(value);
+        assert!(apbf.contains_count(value) >= k);
+
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        for _ in 0..1000 {
+            apbf.insert(rng.gen::<usize>());
+        }
+        for _ in 0..1000 {
+            let v = rng.gen::<usize>();
+            assert_eq!(apbf.contains(v), apbf.contains_count(v) >= k);
+        }
+    }
+
+    #[test]
+    fn test_age_of() {
+        let k = 10;
+        let l = 20;
+        // Seeded so that the value does not turn into a false positive.
+        let mut apbf = APBF::with_seed(k, l, 256, 0);
+        let value = 42usize;
+        assert_eq!(apbf.age_of(value), None);
+
+        apbf.insert(value);
+        assert!(apbf.age_of(value).unwrap().contains(&0));
+
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        let g = apbf.generation();
+        for a in 1..=l as u64 {
+            for _ in 0..g {
+                apbf.insert(rng.gen::<usize>());
+            }
+            let age = apbf.age_of(value).unwrap();
+            assert!(age.contains(&a), "age {} is not in {:?}", a, age);
+        }
+        for _ in 0..apbf.slack() {
+            apbf.insert(rng.gen::<usize>());
+        }
+        assert_eq!(apbf.age_of(value), None);
+    }
+
+    #[test]
+    fn test_insert_if_absent() {
+        let mut apbf: APBF<u64, _> = APBF::with_generation(4, 3, 1000, 10);
+        assert!(apbf.insert_if_absent(1));
+        for _ in 0..100 {
+            assert!(!apbf.insert_if_absent(1));
+        }
+        // Duplicates did not advance the generation.
+        assert_eq!(apbf.shifts, 0);
+        assert_eq!(apbf.n, 1);
+        assert!(apbf.insert_if_absent(2));
+        assert_eq!(apbf.n, 2);
+    }
+
+    #[test]
+    fn test_debug() {
+        let mut apbf: APBF<u64, _> = APBF::with_generation(4, 3, 1000, 10);
+        for v in 0..15 {
+            apbf.insert(v);
+        }
+        assert_eq!((apbf.k(), apbf.l(), apbf.m()), (4, 3, 1000));
+        assert_eq!(apbf.inserts_since_shift(), 5);
+        let debug = format!("{:?}", apbf);
+        assert!(debug.starts_with("APBF { k: 4, l: 3, m: 1000, g: 10,"));
+        assert!(debug.contains("n: 5, p: 6, shifts: 1, ones: ["));
+    }
+
+    #[test]
+    fn test_unsized() {
+        let mut apbf: APBF<str, _> = APBF::with_seed(4, 3, 1000, 0);
+        let value = String::from("abc");
+        apbf.insert(value.as_str());
+        assert!(apbf.contains("abc"));
+
+        // Unsized values are hashed the same as references to them.
+        let mut refs: APBF<&str, _> = APBF::with_seed(4, 3, 1000, 0);
+        refs.insert("abc");
+        assert_eq!(apbf.raw_bits(), refs.raw_bits());
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let apbf: APBF<u64, _> = APBF::new(10, 7, 1000);
+        let bits = (10 + 7) * 16 * 8;
+        assert!(apbf.memory_usage() > bits);
+        assert_eq!(apbf.memory_usage(), analysis::estimated_memory(10, 7, 1000));
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    fn test_stats() {
+        let mut apbf: APBF<u64, _> = APBF::with_generation(4, 3, 1000, 10);
+        for v in 0..25 {
+            apbf.insert(v);
+        }
+        apbf.advance_by(100);
+        apbf.clear();
+        assert!(!apbf.contains(0));
+        apbf.insert(0);
+        assert!(apbf.contains(0));
+        assert_eq!(apbf.total_inserts(), 26);
+        assert_eq!(apbf.total_shifts(), 102);
+        assert_eq!((apbf.queries(), apbf.query_hits()), (2, 1));
+    }
+
+    #[test]
+    fn test_advance() {
+        let k = 10;
+        let l = 20;
+        let mut apbf = APBF::with_generation(k, l, 256, u64::MAX);
+        let value = 42usize;
+        apbf.insert(value);
+
+        for a in 1..=l as u64 {
+            apbf.advance();
+            assert!(apbf.age_of(value).unwrap().contains(&a));
+        }
+        apbf.advance();
+        assert!(!apbf.contains(value));
+
+        apbf.insert(value);
+        apbf.advance_by(1000);
+        assert!(apbf.bits.iter().all(|&w| w == 0));
+        assert_eq!(apbf.shifts, (l + 1 + 1000) as u64);
+        assert_eq!((apbf.p as u64 + apbf.shifts) % apbf.s as u64, 0);
+    }
+
     #[test]
     fn test_contains_immediately() {
         let mut apbf = APBF::new(10, 20, 64);
@@ -0,0 +1,165 @@
+//! Empirical evaluation of filters against the exact set of recent values.
+//!
+//! [`evaluate`] feeds a stream to a filter, querying each value before
+//! inserting it, and compares the answers with a ring buffer of the last
+//! `window` values. It helps to verify that parameters chosen for a filter
+//! meet the requirements on a realistic stream.
+//!
+//! ```
+//! use apbf::eval::{evaluate, Synthetic};
+//! use apbf::APBF;
+//!
+//! let mut apbf = APBF::new(10, 7, 1024);
+//! let window = apbf.window() as usize;
+//! let report = evaluate(&mut apbf, window, Synthetic::new(0, 1 << 16).take(100_000));
+//! assert_eq!(report.false_negatives, 0);
+//! println!("false positive rate: {}", report.false_positive_rate());
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+
+use crate::filter::ProbabilisticFilter;
+
+/// Result of [`evaluate`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Number of queries for values in the window.
+    pub positives: u64,
+    /// Number of queries for values not in the window.
+    pub negatives: u64,
+    /// Number of values not in the window reported as held.
+    pub false_positives: u64,
+    /// Number of values in the window reported as not held.
+    pub false_negatives: u64,
+}
+
+impl Report {
+    /// Returns the ratio of false positives to the queries for values
+    /// not in the window, or zero if there are none.
+    pub fn false_positive_rate(&self) -> f64 {
+        ratio(self.false_positives, self.negatives)
+    }
+
+    /// Returns the ratio of false negatives to the queries for values
+    /// in the window, or zero if there are none.
+    pub fn false_negative_rate(&self) -> f64 {
+        ratio(self.false_negatives, self.positives)
+    }
+}
+
+fn ratio(n: u64, d: u64) -> f64 {
+    if d == 0 {
+        0.0
+    } else {
+        n as f64 / d as f64
+    }
+}
+
+/// Evaluates `filter` on `stream`, where a value is regarded as held
+/// if it is among the last `window` values of the stream.
+///
+/// Each value is queried and then inserted. An APBF is expected to have
+/// no false negatives when `window` is at most [`APBF::window`](crate::APBF::window).
+/// Values older than its window may still be held in the transition zone,
+/// which counts toward the false positives.
+pub fn evaluate<T, F, I>(filter: &mut F, window: usize, stream: I) -> Report
+where
+    T: Hash + Eq + Clone,
+    F: ProbabilisticFilter<T>,
+    I: IntoIterator<Item = T>,
+{
+    let mut report = Report::default();
+    let mut recent = VecDeque::with_capacity(window + 1);
+    let mut counts: HashMap<T, usize> = HashMap::new();
+    for value in stream {
+        let held = filter.contains(&value);
+        if counts.contains_key(&value) {
+            report.positives += 1;
+            report.false_negatives += !held as u64;
+        } else {
+            report.negatives += 1;
+            report.false_positives += held as u64;
+        }
+
+        filter.insert(&value);
+        *counts.entry(value.clone()).or_insert(0) += 1;
+        recent.push_back(value);
+        if recent.len() > window {
+            let old = recent.pop_front().unwrap();
+            let count = counts.get_mut(&old).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&old);
+            }
+        }
+    }
+    report
+}
+
+/// An infinite stream of pseudo-random values uniformly distributed
+/// in `[0, universe)`, generated with SplitMix64.
+///
+/// A small universe produces many repeated values.
+#[derive(Clone, Debug)]
+pub struct Synthetic {
+    state: u64,
+    universe: u64,
+}
+
+impl Synthetic {
+    /// Creates a stream determined by `seed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `universe` is zero.
+    pub fn new(seed: u64, universe: u64) -> Self {
+        assert!(universe > 0, "universe must be positive");
+        Synthetic {
+            state: seed,
+            universe,
+        }
+    }
+}
+
+impl Iterator for Synthetic {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        Some(((z as u128 * self.universe as u128) >> 64) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis;
+    use crate::{Clearing, Layout, APBF};
+
+    #[test]
+    fn test_evaluate_apbf() {
+        let (k, l, m) = (10, 7, 1024);
+        for &layout in &[Layout::Sliced, Layout::Blocked] {
+            for &clearing in &[Clearing::Eager, Clearing::Amortized] {
+                let mut apbf: APBF<u64, _> = APBF::builder(k, l, m)
+                    .layout(layout)
+                    .clearing(clearing)
+                    .build();
+                let window = apbf.window() as usize;
+                let stream = Synthetic::new(0, 1 << 20).take(200_000);
+                let report = evaluate(&mut apbf, window, stream);
+                assert_eq!(report.false_negatives, 0);
+                assert!(report.positives > 0);
+
+                let bound = analysis::false_positive_rate(k, l, m) * 5.0;
+                let measured = report.false_positive_rate();
+                assert!(measured < bound, "measured {}, bound {}", measured, bound);
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! C API over byte strings.
+//!
+//! The declarations are in `include/apbf.h`, which is generated with
+//! `cbindgen --config cbindgen.toml --output include/apbf.h`. CI fails if
+//! it is out of date.
+//! A filter is an opaque pointer created by [`apbf_new`] or [`apbf_load`]
+//! and released by [`apbf_free`]. Filters are not synchronized: a filter
+//! must not be modified while other threads access it.
+//!
+//! Dumps produced by [`apbf_dump`] contain the seed of the hash functions,
+//! so a loaded filter answers queries as the dumped one did. The format is
+//! versioned and portable across architectures.
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::ptr;
+use std::slice;
+
+use crate::core::APBF;
+use crate::hash::sip::SipBuildHasher128;
+use crate::hash::split::SplitHashers;
+
+/// An opaque APBF instance holding byte strings.
+pub struct Apbf {
+    apbf: APBF<[u8], SplitHashers<SipBuildHasher128>>,
+}
+
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len)
+    }
+}
+
+/// Creates a new filter with parameters `k`, `l` and `m`.
+/// Returns `NULL` if any of them is zero.
+#[no_mangle]
+pub extern "C" fn apbf_new(k: usize, l: usize, m: usize) -> *mut Apbf {
+    if k == 0 || l == 0 || m == 0 {
+        return ptr::null_mut();
+    }
+    let seed = RandomState::new().hash_one(0u64);
+    let apbf = APBF::with_seed(k, l, m, seed);
+    Box::into_raw(Box::new(Apbf { apbf }))
+}
+
+/// Releases a filter. Does nothing if `apbf` is `NULL`.
+///
+/// # Safety
+///
+/// `apbf` must be `NULL` or a pointer returned by [`apbf_new`] or
+/// [`apbf_load`] which has not been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn apbf_free(apbf: *mut Apbf) {
+    if !apbf.is_null() {
+        drop(Box::from_raw(apbf));
+    }
+}
+
+/// Inserts the `len` bytes at `data`.
+///
+/// # Safety
+///
+/// `apbf` must be a valid filter, and `data` must point to `len` readable
+/// bytes unless `len` is zero.
+#[no_mangle]
+pub unsafe extern "C" fn apbf_insert_bytes(apbf: *mut Apbf, data: *const u8, len: usize) {
+    (*apbf).apbf.insert(bytes(data, len));
+}
+
+/// Returns `true` if the filter holds the `len` bytes at `data`.
+///
+/// # Safety
+///
+/// Same as [`apbf_insert_bytes`].
+#[no_mangle]
+pub unsafe extern "C" fn apbf_contains_bytes(
+    apbf: *const Apbf,
+    data: *const u8,
+    len: usize,
+) -> bool {
+    (*apbf).apbf.contains(bytes(data, len))
+}
+
+/// Adds the values held by `src` into `dst`. Returns `false` without
+/// modifying `dst` unless they have the same parameters and seed, and have
+/// shifted the same number of times, or if they are the same filter.
+/// See [`APBF::merge`].
+///
+/// # Safety
+///
+/// `dst` and `src` must be valid filters. They may be the same pointer,
+/// which is rejected before either is dereferenced.
+#[no_mangle]
+pub unsafe extern "C" fn apbf_merge(dst: *mut Apbf, src: *const Apbf) -> bool {
+    // `&mut *dst` must not alias `&*src`.
+    if ptr::eq(dst, src) {
+        return false;
+    }
+    let (dst, src) = (&mut *dst, &*src);
+    dst.apbf.seed == src.apbf.seed && dst.apbf.merge(&src.apbf)
+}
+
+/// Writes the state of the filter to `buf` if `cap` is at least the size
+/// of the dump, and returns the size in bytes.
+/// Call it with a `NULL` buffer to query the size.
+///
+/// # Safety
+///
+/// `apbf` must be a valid filter, and `buf` must be `NULL` or point to
+/// `cap` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn apbf_dump(apbf: *const Apbf, buf: *mut u8, cap: usize) -> usize {
+    let dump = (*apbf).apbf.to_bytes();
+    if !buf.is_null() && cap >= dump.len() {
+        ptr::copy_nonoverlapping(dump.as_ptr(), buf, dump.len());
+    }
+    dump.len()
+}
+
+/// Creates a filter from the `len` bytes at `data` written by [`apbf_dump`].
+/// Returns `NULL` if they are not a valid dump.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes unless `len` is zero.
+#[no_mangle]
+pub unsafe extern "C" fn apbf_load(data: *const u8, len: usize) -> *mut Apbf {
+    match APBF::from_seeded_bytes(bytes(data, len)) {
+        Ok(apbf) => Box::into_raw(Box::new(Apbf { apbf })),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffi() {
+        unsafe {
+            let apbf = apbf_new(4, 3, 1000);
+            for v in 0..100u32 {
+                let b = v.to_le_bytes();
+                apbf_insert_bytes(apbf, b.as_ptr(), b.len());
+            }
+            apbf_insert_bytes(apbf, ptr::null(), 0);
+            assert!(apbf_contains_bytes(apbf, ptr::null(), 0));
+
+            let len = apbf_dump(apbf, ptr::null_mut(), 0);
+            let mut buf = vec![0u8; len];
+            assert_eq!(apbf_dump(apbf, buf.as_mut_ptr(), buf.len()), len);
+            let loaded = apbf_load(buf.as_ptr(), buf.len());
+            assert!(!loaded.is_null());
+            for v in 0..1000u32 {
+                let b = v.to_le_bytes();
+                assert_eq!(
+                    apbf_contains_bytes(loaded, b.as_ptr(), b.len()),
+                    apbf_contains_bytes(apbf, b.as_ptr(), b.len())
+                );
+            }
+
+            let b = 1000u32.to_le_bytes();
+            apbf_insert_bytes(loaded, b.as_ptr(), b.len());
+            assert!(apbf_merge(apbf, loaded));
+            assert!(apbf_contains_bytes(apbf, b.as_ptr(), b.len()));
+            let other = apbf_new(4, 3, 1000);
+            assert!(!apbf_merge(apbf, other));
+            apbf_free(other);
+            assert!(!apbf_merge(apbf, apbf));
+
+            assert!(apbf_load(buf.as_ptr(), buf.len() - 1).is_null());
+            // A dump of hash functions not derived from a seed cannot be loaded.
+            let unseeded = APBF::<u64, _>::new(4, 3, 1000).to_bytes();
+            assert!(apbf_load(unseeded.as_ptr(), unseeded.len()).is_null());
+            assert!(apbf_new(0, 3, 1000).is_null());
+            apbf_free(loaded);
+            apbf_free(apbf);
+            apbf_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::hash::Hash;
+
+use crate::core::APBF;
+use crate::hash::Hashers;
+use crate::scalable::ScalableAPBF;
+use crate::store::BitStore;
+
+/// Common interface of approximate membership filters.
+///
+/// Code generic over this trait can use any of the filters in this crate,
+/// or a classic Bloom filter implementing it, interchangeably.
+///
+/// ```
+/// use apbf::{ProbabilisticFilter, APBF};
+///
+/// fn dedup<F: ProbabilisticFilter<u64>>(filter: &mut F, values: &[u64]) -> Vec<u64> {
+///     let mut unique = Vec::new();
+///     for v in values {
+///         if !filter.contains(v) {
+///             filter.insert(v);
+///             unique.push(*v);
+///         }
+///     }
+///     unique
+/// }
+///
+/// let mut apbf = APBF::new(10, 7, 1024);
+/// assert_eq!(dedup(&mut apbf, &[1, 2, 1, 3, 2]), [1, 2, 3]);
+/// ```
+pub trait ProbabilisticFilter<T: ?Sized> {
+    /// Inserts a value to the filter.
+    fn insert(&mut self, value: &T);
+
+    /// Returns `true` if the filter holds a given value.
+    /// It may return `true` for values never inserted.
+    fn contains(&self, value: &T) -> bool;
+}
+
+impl<T, H, S> ProbabilisticFilter<T> for APBF<T, H, S>
+where
+    T: Hash + ?Sized,
+    H: Hashers,
+    S: BitStore,
+{
+    fn insert(&mut self, value: &T) {
+        APBF::insert(self, value)
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        APBF::contains(self, value)
+    }
+}
+
+impl<T, H> ProbabilisticFilter<T> for ScalableAPBF<T, H>
+where
+    T: Hash + ?Sized,
+    H: Hashers,
+{
+    fn insert(&mut self, value: &T) {
+        ScalableAPBF::insert(self, value)
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        ScalableAPBF::contains(self, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::Parameters;
+    use crate::{ApbfWriter, HybridAPBF, MultiWindowAPBF};
+    use std::time::Duration;
+
+    fn check<F: ProbabilisticFilter<u64>>(mut filter: F) {
+        for v in 0..100 {
+            filter.insert(&v);
+        }
+        assert!((0..100).all(|v| filter.contains(&v)));
+    }
+
+    #[test]
+    fn test_filters() {
+        check(APBF::new(4, 3, 1000));
+        check(ScalableAPBF::new(4, 3, 1000));
+        check(MultiWindowAPBF::new(&[
+            Parameters {
+                k: 4,
+                l: 3,
+                m: 1000,
+            },
+            Parameters {
+                k: 4,
+                l: 6,
+                m: 2000,
+            },
+        ]));
+        check(ApbfWriter::new(APBF::new(4, 3, 1000)));
+    }
+
+    #[test]
+    fn test_unsized() {
+        fn check_str<F: ProbabilisticFilter<str>>(mut filter: F) {
+            filter.insert("apbf");
+            assert!(filter.contains("apbf"));
+        }
+
+        check_str(APBF::new(4, 3, 1000));
+        check_str(ScalableAPBF::new(4, 3, 1000));
+        check_str(ApbfWriter::new(APBF::new(4, 3, 1000)));
+        check_str(HybridAPBF::new(4, 3, 1000, 100, Duration::from_secs(60)));
+    }
+}
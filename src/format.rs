@@ -0,0 +1,387 @@
+// Serialized form of an APBF returned by `APBF::to_bytes` and shared by
+// the files of the `mmap` feature, the archives of the `zerocopy` feature
+// and the dumps of the `ffi` and `wasm-bindgen` features.
+//
+// It consists of a header of `HEADER_WORDS` words, the hash function index
+// of each physical slice and the words of the bit array. The first two parts
+// are also passed to `BitStore::sync`.
+//
+// `VERSION` must be incremented whenever the meaning of any word changes.
+// Decoding rejects unknown versions, so that older programs fail with a clear
+// error instead of misreading newer data.
+
+use std::convert::{Infallible, TryFrom};
+use std::hash::Hash;
+use std::io;
+
+use crate::builder::Builder;
+use crate::core::{Clearing, Layout, APBF, WORD_BITS};
+use crate::hash::sip::SipBuildHasher128;
+use crate::hash::split::SplitHashers;
+use crate::hash::Hashers;
+use crate::store::{BitStore, StoreState};
+
+pub(crate) const MAGIC: u64 = u64::from_le_bytes(*b"APBFMMAP");
+pub(crate) const VERSION: u64 = 1;
+pub(crate) const WORD_BYTES: usize = 8;
+
+// Indices of the header fields.
+pub(crate) const H_MAGIC: usize = 0;
+pub(crate) const H_VERSION: usize = 1;
+pub(crate) const H_K: usize = 2;
+pub(crate) const H_L: usize = 3;
+pub(crate) const H_M: usize = 4;
+pub(crate) const H_G: usize = 5;
+pub(crate) const H_LAYOUT: usize = 6;
+pub(crate) const H_CLEARING: usize = 7;
+pub(crate) const H_SEED: usize = 8;
+pub(crate) const H_N: usize = 9;
+pub(crate) const H_P: usize = 10;
+pub(crate) const H_CURSOR: usize = 11;
+pub(crate) const H_SHIFTS: usize = 12;
+pub(crate) const H_NEXT_ID: usize = 13;
+pub(crate) const H_FLAGS: usize = 14;
+// Bits of the flags.
+pub(crate) const FLAG_SEED: u64 = 1; // the seed is known
+pub(crate) const HEADER_WORDS: usize = 16;
+
+pub(crate) fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// Decoded header.
+pub(crate) struct Header {
+    pub(crate) k: usize,
+    pub(crate) l: usize,
+    pub(crate) m: usize,
+    pub(crate) g: u64,
+    pub(crate) layout: Layout,
+    pub(crate) clearing: Clearing,
+    pub(crate) seed: Option<u64>,
+    pub(crate) n: u64,
+    pub(crate) p: usize,
+    pub(crate) cursor: usize,
+    pub(crate) shifts: u64,
+    pub(crate) next_id: u64,
+}
+
+impl Header {
+    // Number of physical slices.
+    pub(crate) fn s(&self) -> usize {
+        match self.clearing {
+            Clearing::Eager => self.k + self.l,
+            Clearing::Amortized => self.k + self.l + 1,
+        }
+    }
+
+    // Number of words for each slice.
+    pub(crate) fn w(&self) -> usize {
+        self.m.div_ceil(WORD_BITS)
+    }
+
+    // Total number of words following the header.
+    pub(crate) fn body_words(&self) -> usize {
+        let s = self.s();
+        s + s * self.w()
+    }
+
+    pub(crate) fn decode(header: &[u64; HEADER_WORDS]) -> io::Result<Self> {
+        if header[H_MAGIC] != MAGIC {
+            return Err(invalid("not an APBF file".to_string()));
+        }
+        if header[H_VERSION] != VERSION {
+            return Err(invalid(format!(
+                "unsupported version {}",
+                header[H_VERSION]
+            )));
+        }
+        let layout = match header[H_LAYOUT] {
+            0 => Layout::Sliced,
+            1 => Layout::Blocked,
+            v => return Err(invalid(format!("unknown layout {}", v))),
+        };
+        let clearing = match header[H_CLEARING] {
+            0 => Clearing::Eager,
+            1 => Clearing::Amortized,
+            v => return Err(invalid(format!("unknown clearing {}", v))),
+        };
+        // Sizes which do not fit in `usize`, e.g. on 32-bit targets, cannot be
+        // those of a valid structure.
+        let size = |i: usize| {
+            usize::try_from(header[i]).map_err(|_| invalid("inconsistent header".to_string()))
+        };
+        let h = Header {
+            k: size(H_K)?,
+            l: size(H_L)?,
+            m: size(H_M)?,
+            g: header[H_G],
+            layout,
+            clearing,
+            seed: if header[H_FLAGS] & FLAG_SEED != 0 {
+                Some(header[H_SEED])
+            } else {
+                None
+            },
+            n: header[H_N],
+            p: size(H_P)?,
+            cursor: size(H_CURSOR)?,
+            shifts: header[H_SHIFTS],
+            next_id: header[H_NEXT_ID],
+        };
+        // The sizes must be computable without overflow, so that `s`,
+        // `body_words` and the size in bytes can be used unchecked afterwards.
+        let spare = (h.clearing == Clearing::Amortized) as usize;
+        let bytes =
+            h.k.checked_add(h.l)
+                .and_then(|kl| kl.checked_add(spare))
+                .and_then(|s| s.checked_mul(h.w())?.checked_add(s))
+                .and_then(|body| body.checked_add(HEADER_WORDS)?.checked_mul(WORD_BYTES));
+        if bytes.is_none()
+            || h.k == 0
+            || h.l == 0
+            || h.m == 0
+            || h.g == 0
+            || (h.layout == Layout::Blocked && !h.m.is_multiple_of(WORD_BITS))
+            || h.n > h.g
+            || h.p >= h.s()
+            || h.cursor > h.w()
+        {
+            return Err(invalid("inconsistent header".to_string()));
+        }
+        Ok(h)
+    }
+}
+
+impl<T: ?Sized, H: Hashers, S: BitStore> APBF<T, H, S> {
+    // Encodes the header. The seed is zero if unknown, and flagged otherwise.
+    pub(crate) fn header(&self) -> [u64; HEADER_WORDS] {
+        let mut header = [0; HEADER_WORDS];
+        header[H_MAGIC] = MAGIC;
+        header[H_VERSION] = VERSION;
+        header[H_K] = self.k as u64;
+        header[H_L] = self.l as u64;
+        header[H_M] = self.m as u64;
+        header[H_G] = self.g;
+        header[H_LAYOUT] = self.layout as u64;
+        header[H_CLEARING] = self.clearing as u64;
+        header[H_SEED] = self.seed.unwrap_or(0);
+        header[H_N] = self.n;
+        header[H_P] = self.p as u64;
+        header[H_CURSOR] = self.cursor as u64;
+        header[H_SHIFTS] = self.shifts;
+        header[H_NEXT_ID] = self.next_id;
+        if self.seed.is_some() {
+            header[H_FLAGS] |= FLAG_SEED;
+        }
+        header
+    }
+
+    // Passes the header and the hash function indices to the store.
+    pub(crate) fn sync(&mut self) {
+        let header = self.header();
+        self.bits.sync(StoreState {
+            header,
+            ids: &self.ids,
+        });
+    }
+}
+
+impl<T: ?Sized, H: Hashers, S: BitStore> APBF<T, H, S> {
+    /// Serializes the structure into bytes readable by [`APBF::from_bytes`].
+    ///
+    /// The encoding is a sequence of 64-bit little-endian words: a header
+    /// of 16 words holding the magic number `APBFMMAP`, the format version,
+    /// the parameters, the seed of the hash functions if known (zero
+    /// otherwise), the counter, the position of the logical slices and flags
+    /// telling whether the seed is known,
+    /// followed by the hash function index of each physical slice and the bit
+    /// array. The hash functions themselves are not included.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let header = self.header();
+        let bits = self.bits.words();
+        let words = header.iter().chain(&self.ids).chain(bits);
+        let mut bytes = Vec::with_capacity((HEADER_WORDS + self.s + bits.len()) * WORD_BYTES);
+        for w in words {
+            bytes.extend_from_slice(&w.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+impl<T: Hash + ?Sized, H: Hashers> APBF<T, H> {
+    /// Deserializes a structure serialized by [`APBF::to_bytes`].
+    /// `hashers` must compute the same hash values as those of the serialized
+    /// structure.
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if `bytes`
+    /// is not a valid encoding, including one of an unsupported version.
+    pub fn from_bytes(bytes: &[u8], hashers: H) -> io::Result<Self> {
+        Self::decode(bytes, |_| hashers)
+    }
+
+    // Deserializes a structure into memory. The hash functions are created
+    // from the decoded header, and are assumed to be seeded with its seed.
+    fn decode<F>(bytes: &[u8], make_hashers: F) -> io::Result<Self>
+    where
+        F: FnOnce(&Header) -> H,
+    {
+        if !bytes.len().is_multiple_of(WORD_BYTES) || bytes.len() < HEADER_WORDS * WORD_BYTES {
+            return Err(invalid(format!("unexpected size {}", bytes.len())));
+        }
+        let mut words = bytes.chunks_exact(WORD_BYTES).map(|b| {
+            let mut word = [0u8; WORD_BYTES];
+            word.copy_from_slice(b);
+            u64::from_le_bytes(word)
+        });
+        let mut header = [0; HEADER_WORDS];
+        for (h, w) in header.iter_mut().zip(&mut words) {
+            *h = w;
+        }
+        let header = Header::decode(&header)?;
+        if bytes.len() != (HEADER_WORDS + header.body_words()) * WORD_BYTES {
+            return Err(invalid(format!("unexpected size {}", bytes.len())));
+        }
+
+        let builder = Builder::new(header.k, header.l, header.m)
+            .hashers(make_hashers(&header))
+            .layout(header.layout)
+            .clearing(header.clearing)
+            .generation(header.g);
+        let ids = (&mut words).take(header.s()).collect();
+        let bits = words.collect();
+        let mut apbf = match Self::try_from_builder(builder, |_, _| Ok::<_, Infallible>(bits)) {
+            Ok(apbf) => apbf,
+            Err(e) => match e {},
+        };
+        apbf.n = header.n;
+        apbf.p = header.p;
+        apbf.cursor = header.cursor;
+        apbf.shifts = header.shifts;
+        apbf.next_id = header.next_id;
+        apbf.seed = header.seed;
+        apbf.ids = ids;
+        Ok(apbf)
+    }
+}
+
+impl<T: Hash + ?Sized> APBF<T, SplitHashers<SipBuildHasher128>> {
+    /// Deserializes a structure created by [`APBF::with_seed`] and serialized
+    /// by [`APBF::to_bytes`], restoring the hash functions from the seed.
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if `bytes`
+    /// is not a valid encoding, or has no seed because the structure was
+    /// created with other hash functions.
+    ///
+    /// ```
+    /// use apbf::APBF;
+    ///
+    /// let mut apbf: APBF<&str, _> = APBF::with_seed(10, 7, 1024, 42);
+    /// apbf.insert("a");
+    /// let restored = APBF::<&str, _>::from_seeded_bytes(&apbf.to_bytes()).unwrap();
+    /// assert!(restored.contains("a"));
+    /// ```
+    pub fn from_seeded_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let mut seeded = true;
+        let apbf = Self::decode(bytes, |header| {
+            seeded = header.seed.is_some();
+            SplitHashers::with_seed(header.m as u64, header.seed.unwrap_or(0))
+        })?;
+        if !seeded {
+            return Err(invalid("the seed is unknown".to_string()));
+        }
+        Ok(apbf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::km::KMHashers;
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        for &layout in &[Layout::Sliced, Layout::Blocked] {
+            for &clearing in &[Clearing::Eager, Clearing::Amortized] {
+                let hashers = KMHashers::new(1024);
+                let mut apbf: APBF<u64, _> = Builder::new(4, 3, 1024)
+                    .hashers(hashers.clone())
+                    .layout(layout)
+                    .clearing(clearing)
+                    .build();
+                for v in 0..1000 {
+                    apbf.insert(v);
+                }
+
+                let mut restored = APBF::from_bytes(&apbf.to_bytes(), hashers).unwrap();
+                for v in 0..2000 {
+                    assert_eq!(restored.contains(v), apbf.contains(v));
+                }
+                // Both continue from the same state.
+                for v in 1000..2000 {
+                    apbf.insert(v);
+                    restored.insert(v);
+                }
+                assert_eq!(restored.to_bytes(), apbf.to_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn test_bytes_full_generation() {
+        // Insertions shift lazily, so the counter may equal the generation.
+        let mut apbf: APBF<u64, _> = APBF::with_seed(4, 3, 1000, 0);
+        let g = apbf.generation();
+        for v in 0..g {
+            apbf.insert(v);
+        }
+        assert_eq!(apbf.inserts_since_shift(), g);
+
+        let mut restored = APBF::<u64, _>::from_seeded_bytes(&apbf.to_bytes()).unwrap();
+        assert!((0..g).all(|v| restored.contains(v)));
+        restored.insert(g);
+        assert_eq!(restored.inserts_since_shift(), 1);
+    }
+
+    #[test]
+    fn test_bytes_invalid() {
+        let apbf: APBF<u64, _> = APBF::with_seed(4, 3, 1000, 0);
+        let bytes = apbf.to_bytes();
+
+        let mut newer = bytes.clone();
+        newer[H_VERSION * WORD_BYTES] = VERSION as u8 + 1;
+        let err = APBF::<u64, _>::from_seeded_bytes(&newer).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            format!("unsupported version {}", VERSION + 1)
+        );
+
+        for b in &[&bytes[..bytes.len() - 8], &bytes[..100], &[0u8; 128][..]] {
+            let err = APBF::<u64, _>::from_seeded_bytes(b).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+
+        // Sizes overflowing `usize`.
+        for &i in &[H_K, H_L] {
+            let mut huge = bytes.clone();
+            huge[i * WORD_BYTES..(i + 1) * WORD_BYTES].copy_from_slice(&u64::MAX.to_le_bytes());
+            let err = APBF::<u64, _>::from_seeded_bytes(&huge).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(err.to_string(), "inconsistent header");
+        }
+    }
+
+    #[test]
+    fn test_bytes_unseeded() {
+        let hashers = KMHashers::new(1000);
+        let mut apbf: APBF<u64, _> = APBF::with_hashers(4, 3, 1000, hashers.clone());
+        apbf.insert(0);
+        let bytes = apbf.to_bytes();
+
+        let err = APBF::<u64, _>::from_seeded_bytes(&bytes).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let restored: APBF<u64, _> = APBF::from_bytes(&bytes, hashers).unwrap();
+        assert_eq!(restored.seed, None);
+        assert!(restored.contains(0));
+    }
+}
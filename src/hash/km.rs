@@ -2,7 +2,33 @@ use std::borrow::Borrow;
 use std::collections::hash_map::RandomState;
 use std::hash::{BuildHasher, Hash, Hasher};
 
-use crate::hash::{Hashers, Hashes};
+use crate::hash::sip::{SipBuildHasher128, SipHasher128};
+use crate::hash::{HashPairs, Hasher128, Hashers, Hashes};
+
+/// Strategy to map a 64-bit hash value onto the range `[0, p)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reduction {
+    /// Lemire's multiply-shift range reduction, which replaces the division
+    /// with a widening multiplication.
+    /// See https://lemire.me/blog/2016/06/27/a-fast-alternative-to-the-modulo-reduction/
+    FastRange,
+    /// Plain modulo reduction. This produces the same indices as earlier
+    /// versions of this crate.
+    Modulo,
+    /// Bitmask reduction `x & (p - 1)`. Only applicable when `p` is a power of two.
+    Mask,
+}
+
+impl Reduction {
+    #[inline]
+    fn reduce(self, x: u64, p: u64) -> u64 {
+        match self {
+            Reduction::FastRange => ((x as u128 * p as u128) >> 64) as u64,
+            Reduction::Modulo => x % p,
+            Reduction::Mask => x & (p - 1),
+        }
+    }
+}
 
 /// A logical set of hash functions derived from two inner hash functions
 /// with Kirsch-Mitzenmacher Optimization.
@@ -15,11 +41,54 @@ where
     bh1: B1,
     bh2: B2,
     p: u64,
+    reduction: Reduction,
 }
 
 impl KMHashers<RandomState, RandomState> {
     pub fn new(p: u64) -> Self {
-        KMHashers::with_build_hashers(p, RandomState::new(), RandomState::new())
+        Self::with_reduction(p, Reduction::FastRange)
+    }
+
+    /// Creates hashers which map hash values with a given reduction.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `reduction` is `Reduction::Mask` and `p` is not a power of two.
+    pub fn with_reduction(p: u64, reduction: Reduction) -> Self {
+        assert!(
+            reduction != Reduction::Mask || p.is_power_of_two(),
+            "mask reduction requires a power of two, got {}",
+            p
+        );
+        KMHashers {
+            reduction,
+            ..KMHashers::with_build_hashers(p, RandomState::new(), RandomState::new())
+        }
+    }
+}
+
+impl KMHashers<SipBuildHasher128, SipBuildHasher128> {
+    /// Creates hashers based on SipHash-1-3 keyed with a secret `key`.
+    ///
+    /// As long as the key is kept secret, an adversary cannot craft values
+    /// colliding in the filter to raise the false positive rate.
+    /// Unlike [`new`](KMHashers::new), the hash values are determined by
+    /// the key, so a filter can be persisted and restored along with it.
+    pub fn with_key(p: u64, key: [u8; 16]) -> Self {
+        let mut k0 = [0u8; 8];
+        let mut k1 = [0u8; 8];
+        k0.copy_from_slice(&key[..8]);
+        k1.copy_from_slice(&key[8..]);
+        let (k0, k1) = (u64::from_le_bytes(k0), u64::from_le_bytes(k1));
+
+        // Derive the keys of the two inner hash functions with SipHash itself.
+        let derive = |i: u8| {
+            let mut h = SipHasher128::new_with_keys(k0, k1);
+            h.write_u8(i);
+            let (a, b) = h.finish128();
+            SipBuildHasher128::with_keys(a, b)
+        };
+        Self::with_build_hashers(p, derive(1), derive(2))
     }
 }
 
@@ -28,12 +97,25 @@ where
     B1: BuildHasher,
     B2: BuildHasher,
 {
-    fn with_build_hashers(p: u64, bh1: B1, bh2: B2) -> Self
+    /// Creates hashers returning values in `[0, p)` from two inner hash functions.
+    /// `bh1` and `bh2` must produce independent hash values.
+    pub fn with_build_hashers(p: u64, bh1: B1, bh2: B2) -> Self
     where
         B1: BuildHasher,
         B2: BuildHasher,
     {
-        Self { p, bh1, bh2 }
+        Self {
+            p,
+            bh1,
+            bh2,
+            reduction: Reduction::FastRange,
+        }
+    }
+
+    #[cfg(feature = "wyhash")]
+    pub(crate) fn set_build_hashers(&mut self, bh1: B1, bh2: B2) {
+        self.bh1 = bh1;
+        self.bh2 = bh2;
     }
 }
 
@@ -44,18 +126,25 @@ where
 {
     type H = KMHashes;
 
-    fn hash<Q: Hash, V: Borrow<Q>>(&self, value: V) -> KMHashes {
+    fn hash<Q: Hash + ?Sized, V: Borrow<Q>>(&self, value: V) -> KMHashes {
         let value = value.borrow();
-        let mut h1 = self.bh1.build_hasher();
-        let mut h2 = self.bh2.build_hasher();
-        value.hash(&mut h1);
-        value.hash(&mut h2);
-
-        KMHashes {
-            x1: h1.finish() % self.p,
-            x2: h2.finish() % self.p,
-            p: self.p,
-        }
+        let x1 = self.bh1.hash_one(value);
+        let x2 = self.bh2.hash_one(value);
+        KMHashes::new(x1, x2, self.p, self.reduction)
+    }
+}
+
+impl<B1, B2> HashPairs for KMHashers<B1, B2>
+where
+    B1: BuildHasher,
+    B2: BuildHasher,
+{
+    fn to_pair(hashes: &KMHashes) -> (u64, u64) {
+        hashes.pair()
+    }
+
+    fn hash_pair(&self, (x1, x2): (u64, u64)) -> KMHashes {
+        KMHashes::new(x1, x2, self.p, self.reduction)
     }
 }
 
@@ -64,11 +153,106 @@ pub struct KMHashes {
     x1: u64,
     x2: u64,
     p: u64,
+    reduction: Reduction,
+}
+
+impl KMHashes {
+    /// Creates hashes from two 64-bit hash values of a value.
+    /// Applying it again to the stored values yields the same hashes.
+    pub(crate) fn new(x1: u64, x2: u64, p: u64, reduction: Reduction) -> Self {
+        match reduction {
+            // Keep the full 64-bit values so that the reduction in `get`
+            // is applied to a uniformly distributed input.
+            Reduction::FastRange => KMHashes {
+                x1,
+                x2,
+                p,
+                reduction,
+            },
+            // An odd stride visits every residue modulo a power of two,
+            // so the derived indices do not collapse onto a subgroup.
+            Reduction::Mask => KMHashes {
+                x1,
+                x2: x2 | 1,
+                p,
+                reduction,
+            },
+            Reduction::Modulo => KMHashes {
+                x1: x1 % p,
+                x2: x2 % p,
+                p,
+                reduction,
+            },
+        }
+    }
+
+    // Returns the hash values passed to `new` after the adjustment.
+    pub(crate) fn pair(&self) -> (u64, u64) {
+        (self.x1, self.x2)
+    }
 }
 
 impl Hashes for KMHashes {
+    #[inline]
     fn get(&self, i: u64) -> u64 {
-        // TODO: https://lemire.me/blog/2016/06/27/a-fast-alternative-to-the-modulo-reduction/ ?
-        (self.x1 + i * self.x2) % self.p
+        let x = self.x1.wrapping_add(i.wrapping_mul(self.x2));
+        self.reduction.reduce(x, self.p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_in_range() {
+        for &reduction in &[Reduction::FastRange, Reduction::Modulo, Reduction::Mask] {
+            for &p in &[1u64, 7, 64, 1000] {
+                if reduction == Reduction::Mask && !p.is_power_of_two() {
+                    continue;
+                }
+                let hashers = KMHashers::with_reduction(p, reduction);
+                for v in 0..100u64 {
+                    let hashes: KMHashes = hashers.hash(v);
+                    for i in 0..32 {
+                        assert!(hashes.get(i) < p);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_mask_requires_power_of_two() {
+        KMHashers::with_reduction(1000, Reduction::Mask);
+    }
+
+    #[test]
+    fn test_with_key() {
+        let key = *b"0123456789abcdef";
+        let a = KMHashers::with_key(1000, key);
+        let b = KMHashers::with_key(1000, key);
+        let c = KMHashers::with_key(1000, *b"0123456789abcdeg");
+        let get = |h: &KMHashers<_, _>, v: u64| {
+            let hashes: KMHashes = h.hash(v);
+            (0..8).map(|i| hashes.get(i)).collect::<Vec<_>>()
+        };
+        assert!((0..100).all(|v| get(&a, v) == get(&b, v)));
+        assert!((0..100).any(|v| get(&a, v) != get(&c, v)));
+    }
+
+    #[test]
+    fn test_modulo_compatibility() {
+        let p = 1000;
+        let hashers = KMHashers::with_reduction(p, Reduction::Modulo);
+        let v = 42usize;
+        let x1 = hashers.bh1.hash_one(v) % p;
+        let x2 = hashers.bh2.hash_one(v) % p;
+
+        let hashes: KMHashes = hashers.hash(v);
+        for i in 0..32 {
+            assert_eq!(hashes.get(i), (x1 + i * x2) % p);
+        }
     }
 }
@@ -1,13 +1,63 @@
 use std::borrow::Borrow;
-use std::hash::Hash;
+use std::hash::{Hash, Hasher};
 
 pub trait Hashers {
     type H: Hashes;
-    fn hash<Q: Hash, V: Borrow<Q>>(&self, value: V) -> Self::H;
+    fn hash<Q: Hash + ?Sized, V: Borrow<Q>>(&self, value: V) -> Self::H;
 }
 
 pub trait Hashes {
     fn get(&self, i: u64) -> u64;
 }
 
+/// Hash functions which can be re-initialized from a seed.
+pub trait SeedableHashers: Hashers {
+    /// `true` if the hash functions derived from a seed are the ones
+    /// [`APBF::from_seeded_bytes`](crate::APBF::from_seeded_bytes) rebuilds
+    /// from it, so that the seed is recorded by
+    /// [`APBF::reset_with_seed`](crate::APBF::reset_with_seed).
+    /// `false` by default.
+    const RESTORABLE: bool = false;
+
+    /// Replaces the inner hash functions with ones derived from `seed`,
+    /// keeping the range of hash values.
+    fn reseed(&mut self, seed: u64);
+}
+
+/// Hash functions derived from a pair of 64-bit hash values of a value,
+/// which can be recorded and turned back into hash values later,
+/// e.g. by [`WalAPBF`](crate::WalAPBF).
+pub trait HashPairs: Hashers {
+    /// Returns the pair of hash values which `hashes` is derived from.
+    fn to_pair(hashes: &Self::H) -> (u64, u64);
+
+    /// Returns the hash values derived from a pair returned by [`to_pair`](Self::to_pair).
+    fn hash_pair(&self, pair: (u64, u64)) -> Self::H;
+}
+
+/// A [`Hasher`] which can produce a 128-bit hash value.
+pub trait Hasher128: Hasher {
+    /// Returns the 128-bit hash value as two 64-bit halves.
+    fn finish128(&self) -> (u64, u64);
+}
+
+// Derives two independent 64-bit seeds from a seed with SplitMix64.
+pub(crate) fn split_seed(seed: u64) -> (u64, u64) {
+    fn next(x: &mut u64) -> u64 {
+        *x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = *x;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+    let mut x = seed;
+    let s1 = next(&mut x);
+    let s2 = next(&mut x);
+    (s1, s2)
+}
+
 pub mod km;
+pub mod sip;
+pub mod split;
+#[cfg(feature = "wyhash")]
+pub mod wy;
@@ -0,0 +1,286 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+use crate::hash::Hasher128;
+
+/// An implementation of SipHash-1-3 producing 128-bit hash values.
+#[derive(Clone, Debug)]
+pub struct SipHasher128 {
+    state: State<1, 3>,
+}
+
+// The state of SipHash-c-d, which is only instantiated with other numbers
+// of rounds to be checked against the reference test vectors of SipHash-2-4.
+#[derive(Clone, Debug)]
+struct State<const C: usize, const D: usize> {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    tail: u64,    // unprocessed bytes in little endian
+    ntail: usize, // number of bytes in tail
+    length: usize,
+}
+
+// Loads up to 7 bytes as a little endian integer.
+#[inline]
+fn u8to64_le(buf: &[u8]) -> u64 {
+    debug_assert!(buf.len() < 8);
+    buf.iter()
+        .enumerate()
+        .fold(0, |x, (i, &b)| x | (b as u64) << (8 * i))
+}
+
+impl SipHasher128 {
+    /// Creates a hasher keyed with `k0` and `k1`.
+    pub fn new_with_keys(k0: u64, k1: u64) -> Self {
+        SipHasher128 {
+            state: State::new_with_keys128(k0, k1),
+        }
+    }
+}
+
+impl<const C: usize, const D: usize> State<C, D> {
+    fn new_with_keys128(k0: u64, k1: u64) -> Self {
+        let mut state = Self::new_with_keys(k0, k1);
+        state.v1 ^= 0xee;
+        state
+    }
+
+    fn new_with_keys(k0: u64, k1: u64) -> Self {
+        State {
+            v0: k0 ^ 0x736f_6d65_7073_6575,
+            v1: k1 ^ 0x646f_7261_6e64_6f6d,
+            v2: k0 ^ 0x6c79_6765_6e65_7261,
+            v3: k1 ^ 0x7465_6462_7974_6573,
+            tail: 0,
+            ntail: 0,
+            length: 0,
+        }
+    }
+
+    #[inline]
+    fn round(&mut self) {
+        self.v0 = self.v0.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(13);
+        self.v1 ^= self.v0;
+        self.v0 = self.v0.rotate_left(32);
+        self.v2 = self.v2.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(16);
+        self.v3 ^= self.v2;
+        self.v0 = self.v0.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(21);
+        self.v3 ^= self.v0;
+        self.v2 = self.v2.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(17);
+        self.v1 ^= self.v2;
+        self.v2 = self.v2.rotate_left(32);
+    }
+
+    #[inline]
+    fn compress(&mut self, m: u64) {
+        self.v3 ^= m;
+        for _ in 0..C {
+            self.round();
+        }
+        self.v0 ^= m;
+    }
+
+    fn write(&mut self, msg: &[u8]) {
+        self.length += msg.len();
+
+        let mut msg = msg;
+        if self.ntail != 0 {
+            let needed = 8 - self.ntail;
+            let fill = std::cmp::min(needed, msg.len());
+            self.tail |= u8to64_le(&msg[..fill]) << (8 * self.ntail);
+            if msg.len() < needed {
+                self.ntail += msg.len();
+                return;
+            }
+            self.compress(self.tail);
+            self.tail = 0;
+            self.ntail = 0;
+            msg = &msg[needed..];
+        }
+
+        let mut chunks = msg.chunks_exact(8);
+        for chunk in &mut chunks {
+            let mut b = [0; 8];
+            b.copy_from_slice(chunk);
+            self.compress(u64::from_le_bytes(b));
+        }
+        let rest = chunks.remainder();
+        self.tail = u8to64_le(rest);
+        self.ntail = rest.len();
+    }
+
+    // Processes the last block and returns the state before finalization.
+    fn last(&self) -> Self {
+        let mut state = self.clone();
+        let b = ((self.length as u64 & 0xff) << 56) | self.tail;
+        state.compress(b);
+        state
+    }
+
+    fn finalize(&mut self) -> u64 {
+        for _ in 0..D {
+            self.round();
+        }
+        self.v0 ^ self.v1 ^ self.v2 ^ self.v3
+    }
+
+    fn finish128(&self) -> (u64, u64) {
+        let mut state = self.last();
+        state.v2 ^= 0xee;
+        let h1 = state.finalize();
+        state.v1 ^= 0xdd;
+        let h2 = state.finalize();
+        (h1, h2)
+    }
+}
+
+impl Hasher for SipHasher128 {
+    fn write(&mut self, msg: &[u8]) {
+        self.state.write(msg);
+    }
+
+    fn finish(&self) -> u64 {
+        self.finish128().0
+    }
+}
+
+impl Hasher128 for SipHasher128 {
+    fn finish128(&self) -> (u64, u64) {
+        self.state.finish128()
+    }
+}
+
+/// A [`BuildHasher`] creating [`SipHasher128`]s with fixed keys.
+#[derive(Clone, Debug)]
+pub struct SipBuildHasher128 {
+    k0: u64,
+    k1: u64,
+}
+
+impl SipBuildHasher128 {
+    /// Creates a builder with random keys.
+    pub fn new() -> Self {
+        let s = RandomState::new();
+        Self::with_keys(s.hash_one(0u8), s.hash_one(1u8))
+    }
+
+    /// Creates a builder with given keys.
+    pub fn with_keys(k0: u64, k1: u64) -> Self {
+        SipBuildHasher128 { k0, k1 }
+    }
+}
+
+impl Default for SipBuildHasher128 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BuildHasher for SipBuildHasher128 {
+    type Hasher = SipHasher128;
+
+    fn build_hasher(&self) -> SipHasher128 {
+        SipHasher128::new_with_keys(self.k0, self.k1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash128(bh: &SipBuildHasher128, chunks: &[&[u8]]) -> (u64, u64) {
+        let mut h = bh.build_hasher();
+        for chunk in chunks {
+            h.write(chunk);
+        }
+        h.finish128()
+    }
+
+    #[test]
+    fn test_streaming() {
+        let bh = SipBuildHasher128::with_keys(1, 2);
+        let msg = (0..64u8).collect::<Vec<_>>();
+        for n in 0..msg.len() {
+            let expected = hash128(&bh, &[&msg[..n]]);
+            for i in 0..=n {
+                assert_eq!(hash128(&bh, &[&msg[..i], &msg[i..n]]), expected);
+            }
+        }
+    }
+
+    // The first vectors of `vectors_sip128` in the reference implementation
+    // of SipHash-2-4, hashing bytes 0, 1, ... with key 0, 1, ..., 15.
+    #[test]
+    fn test_sip128_vectors() {
+        let vectors: [u128; 4] = [
+            0xa381_7f04_ba25_a8e6_6df6_7214_c755_0293,
+            0xda87_c1d8_6b99_af44_3476_5911_9b22_fc45,
+            0x8177_228d_a4a4_5dc7_fca3_8bde_f60a_ffe4,
+            0x9c70_b60c_5267_a94e_5f33_b6b0_2985_ed51,
+        ];
+        let k0 = u64::from_le_bytes([0, 1, 2, 3, 4, 5, 6, 7]);
+        let k1 = u64::from_le_bytes([8, 9, 10, 11, 12, 13, 14, 15]);
+        let msg = (0..4u8).collect::<Vec<_>>();
+        for (n, &v) in vectors.iter().enumerate() {
+            let mut h = State::<2, 4>::new_with_keys128(k0, k1);
+            h.write(&msg[..n]);
+            let (h1, h2) = h.finish128();
+            let mut bytes = [0; 16];
+            bytes[..8].copy_from_slice(&h1.to_le_bytes());
+            bytes[8..].copy_from_slice(&h2.to_le_bytes());
+            assert_eq!(u128::from_be_bytes(bytes), v, "length {}", n);
+        }
+    }
+
+    // Pins the output of SipHash-1-3, on which persisted filters depend.
+    #[test]
+    fn test_sip13_stable() {
+        let k0 = u64::from_le_bytes([0, 1, 2, 3, 4, 5, 6, 7]);
+        let k1 = u64::from_le_bytes([8, 9, 10, 11, 12, 13, 14, 15]);
+        let msg = (0..63u8).collect::<Vec<_>>();
+        let vectors: [(usize, u64, u64); 3] = [
+            (0, 0xbea5_8827_b2bc_7ee7, 0x0130_30dd_6adb_62fd),
+            (15, 0x6c52_bdb2_0555_7ec1, 0x0901_7e1e_eccd_2129),
+            (63, 0x6f42_fe4e_e300_584c, 0xad60_52a7_0a6b_9f07),
+        ];
+        for &(n, h1, h2) in &vectors {
+            let mut h = SipHasher128::new_with_keys(k0, k1);
+            h.write(&msg[..n]);
+            assert_eq!(h.finish128(), (h1, h2), "length {}", n);
+        }
+    }
+
+    // Longer messages are checked on the 64-bit variant against
+    // `std::hash::SipHasher`, which implements SipHash-2-4.
+    #[test]
+    #[allow(deprecated)]
+    fn test_sip64_std() {
+        let msg = (0..100u8).collect::<Vec<_>>();
+        for n in 0..msg.len() {
+            let mut h = State::<2, 4>::new_with_keys(1, 2);
+            h.write(&msg[..n / 3]);
+            h.write(&msg[n / 3..n]);
+            let mut state = h.last();
+            state.v2 ^= 0xff;
+
+            let mut expected = std::hash::SipHasher::new_with_keys(1, 2);
+            expected.write(&msg[..n]);
+            assert_eq!(state.finalize(), expected.finish(), "length {}", n);
+        }
+    }
+
+    #[test]
+    fn test_keys() {
+        let msg: &[u8] = b"age-partitioned bloom filter";
+        let h1 = hash128(&SipBuildHasher128::with_keys(1, 2), &[msg]);
+        let h2 = hash128(&SipBuildHasher128::with_keys(1, 3), &[msg]);
+        assert_ne!(h1, h2);
+        assert_ne!(h1.0, h1.1);
+    }
+}
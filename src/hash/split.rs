@@ -0,0 +1,104 @@
+use std::borrow::Borrow;
+use std::hash::{BuildHasher, Hash};
+
+use crate::hash::km::{KMHashes, Reduction};
+use crate::hash::sip::SipBuildHasher128;
+use crate::hash::{split_seed, HashPairs, Hasher128, Hashers, SeedableHashers};
+
+/// A logical set of hash functions derived from a single 128-bit hash value
+/// with Kirsch-Mitzenmacher Optimization.
+///
+/// Compared to [`KMHashers`](crate::hash::km::KMHashers), which hashes a value
+/// twice with independent hash functions, it passes over the value only once,
+/// and then splits the result into two 64-bit halves.
+#[derive(Clone)]
+pub struct SplitHashers<B> {
+    bh: B,
+    p: u64,
+    reduction: Reduction,
+}
+
+impl SplitHashers<SipBuildHasher128> {
+    /// Creates hashers based on SipHash-1-3 with random keys.
+    pub fn new(p: u64) -> Self {
+        Self::with_build_hasher(p, SipBuildHasher128::new())
+    }
+
+    /// Creates hashers based on SipHash-1-3 with keys derived from `seed`.
+    /// Unlike [`new`](Self::new), it needs no source of randomness.
+    pub fn with_seed(p: u64, seed: u64) -> Self {
+        let (k0, k1) = split_seed(seed);
+        Self::with_build_hasher(p, SipBuildHasher128::with_keys(k0, k1))
+    }
+}
+
+impl<B> SplitHashers<B>
+where
+    B: BuildHasher,
+    B::Hasher: Hasher128,
+{
+    /// Creates hashers returning values in `[0, p)` from a 128-bit hash function.
+    pub fn with_build_hasher(p: u64, bh: B) -> Self {
+        SplitHashers {
+            bh,
+            p,
+            reduction: Reduction::FastRange,
+        }
+    }
+}
+
+impl<B> Hashers for SplitHashers<B>
+where
+    B: BuildHasher,
+    B::Hasher: Hasher128,
+{
+    type H = KMHashes;
+
+    fn hash<Q: Hash + ?Sized, V: Borrow<Q>>(&self, value: V) -> KMHashes {
+        let mut h = self.bh.build_hasher();
+        value.borrow().hash(&mut h);
+        let (x1, x2) = h.finish128();
+        KMHashes::new(x1, x2, self.p, self.reduction)
+    }
+}
+
+impl<B> HashPairs for SplitHashers<B>
+where
+    B: BuildHasher,
+    B::Hasher: Hasher128,
+{
+    fn to_pair(hashes: &KMHashes) -> (u64, u64) {
+        hashes.pair()
+    }
+
+    fn hash_pair(&self, (x1, x2): (u64, u64)) -> KMHashes {
+        KMHashes::new(x1, x2, self.p, self.reduction)
+    }
+}
+
+impl SeedableHashers for SplitHashers<SipBuildHasher128> {
+    const RESTORABLE: bool = true;
+
+    fn reseed(&mut self, seed: u64) {
+        let (k0, k1) = split_seed(seed);
+        self.bh = SipBuildHasher128::with_keys(k0, k1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::Hashes;
+
+    #[test]
+    fn test_get_in_range() {
+        let p = 1000;
+        let hashers = SplitHashers::new(p);
+        for v in 0..100u64 {
+            let hashes: KMHashes = hashers.hash(v);
+            for i in 0..32 {
+                assert!(hashes.get(i) < p);
+            }
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+use crate::hash::km::KMHashers;
+use crate::hash::{split_seed, SeedableHashers};
+
+// Default secret of wyhash final version 4. Revision 4.2 changed it
+// to 0x2d358dccaa6c78a5 and others, and its outputs differ.
+const S0: u64 = 0xa076_1d64_78bd_642f;
+const S1: u64 = 0xe703_7ed1_a0b4_28db;
+const S2: u64 = 0x8ebc_6af0_9c88_c6e3;
+const S3: u64 = 0x5899_65cc_7537_4cc3;
+
+#[inline]
+fn mum(a: u64, b: u64) -> (u64, u64) {
+    let r = a as u128 * b as u128;
+    (r as u64, (r >> 64) as u64)
+}
+
+#[inline]
+fn mix(a: u64, b: u64) -> u64 {
+    let (lo, hi) = mum(a, b);
+    lo ^ hi
+}
+
+#[inline]
+fn r8(p: &[u8]) -> u64 {
+    let mut b = [0; 8];
+    b.copy_from_slice(&p[..8]);
+    u64::from_le_bytes(b)
+}
+
+#[inline]
+fn r4(p: &[u8]) -> u64 {
+    let mut b = [0; 4];
+    b.copy_from_slice(&p[..4]);
+    u32::from_le_bytes(b) as u64
+}
+
+#[inline]
+fn r3(p: &[u8]) -> u64 {
+    let k = p.len();
+    ((p[0] as u64) << 16) | ((p[k >> 1] as u64) << 8) | p[k - 1] as u64
+}
+
+/// Computes wyhash (final version 4, before revision 4.2) of bytes with
+/// a given seed and the default secret.
+pub fn wyhash(bytes: &[u8], seed: u64) -> u64 {
+    let len = bytes.len();
+    let mut seed = seed ^ mix(seed ^ S0, S1);
+    let (a, b) = if len <= 16 {
+        if len >= 4 {
+            let q = (len >> 3) << 2;
+            (
+                (r4(bytes) << 32) | r4(&bytes[q..]),
+                (r4(&bytes[len - 4..]) << 32) | r4(&bytes[len - 4 - q..]),
+            )
+        } else if len > 0 {
+            (r3(bytes), 0)
+        } else {
+            (0, 0)
+        }
+    } else {
+        let mut p = bytes;
+        if p.len() > 48 {
+            let mut see1 = seed;
+            let mut see2 = seed;
+            while p.len() > 48 {
+                seed = mix(r8(p) ^ S1, r8(&p[8..]) ^ seed);
+                see1 = mix(r8(&p[16..]) ^ S2, r8(&p[24..]) ^ see1);
+                see2 = mix(r8(&p[32..]) ^ S3, r8(&p[40..]) ^ see2);
+                p = &p[48..];
+            }
+            seed ^= see1 ^ see2;
+        }
+        while p.len() > 16 {
+            seed = mix(r8(p) ^ S1, r8(&p[8..]) ^ seed);
+            p = &p[16..];
+        }
+        // Read the last 16 bytes, which may overlap with processed ones.
+        let tail = &bytes[len - 16..];
+        (r8(tail), r8(&tail[8..]))
+    };
+    let (a, b) = mum(a ^ S1, b ^ seed);
+    mix(a ^ S0 ^ len as u64, b ^ S1)
+}
+
+/// A [`Hasher`] based on wyhash.
+///
+/// Each chunk of bytes written to the hasher is hashed with the current state
+/// as a seed, and integers are folded into the state with a single
+/// multiplication. Therefore it produces different values from [`wyhash`]
+/// applied on the concatenated bytes.
+#[derive(Clone, Debug)]
+pub struct WyHasher {
+    state: u64,
+}
+
+impl WyHasher {
+    /// Creates a hasher with a given seed.
+    pub fn with_seed(seed: u64) -> Self {
+        WyHasher { state: seed }
+    }
+}
+
+impl Hasher for WyHasher {
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        self.state = wyhash(bytes, self.state);
+    }
+
+    #[inline]
+    fn write_u8(&mut self, i: u8) {
+        self.write_u64(i as u64);
+    }
+
+    #[inline]
+    fn write_u16(&mut self, i: u16) {
+        self.write_u64(i as u64);
+    }
+
+    #[inline]
+    fn write_u32(&mut self, i: u32) {
+        self.write_u64(i as u64);
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.state = mix(self.state ^ S0, i ^ S1);
+    }
+
+    #[inline]
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        mix(self.state ^ S2, S3)
+    }
+}
+
+/// A [`BuildHasher`] creating seeded [`WyHasher`]s.
+///
+/// To be used with [`KMHashers`], the two builders
+/// must be given different seeds.
+#[derive(Clone, Debug)]
+pub struct WyBuildHasher {
+    seed: u64,
+}
+
+impl WyBuildHasher {
+    /// Creates a builder with a random seed.
+    pub fn new() -> Self {
+        Self::with_seed(RandomState::new().build_hasher().finish())
+    }
+
+    /// Creates a builder with a given seed.
+    /// Hashers with the same seed always produce the same values.
+    pub fn with_seed(seed: u64) -> Self {
+        WyBuildHasher { seed }
+    }
+}
+
+impl Default for WyBuildHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BuildHasher for WyBuildHasher {
+    type Hasher = WyHasher;
+
+    fn build_hasher(&self) -> WyHasher {
+        WyHasher::with_seed(self.seed)
+    }
+}
+
+impl SeedableHashers for KMHashers<WyBuildHasher, WyBuildHasher> {
+    fn reseed(&mut self, seed: u64) {
+        let (s1, s2) = split_seed(seed);
+        self.set_build_hashers(WyBuildHasher::with_seed(s1), WyBuildHasher::with_seed(s2));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::APBF;
+    use std::collections::HashSet;
+
+    // Test vectors of the reference implementation of final version 4,
+    // hashing the i-th string with seed i.
+    #[test]
+    fn test_wyhash_vectors() {
+        let vectors: [(&str, u64); 7] = [
+            ("", 0x0409_638e_e2bd_e459),
+            ("a", 0xa841_2d09_1b5f_e0a9),
+            ("abc", 0x32dd_92e4_b291_5153),
+            ("message digest", 0x8619_1240_89a3_a16b),
+            ("abcdefghijklmnopqrstuvwxyz", 0x7a43_afb6_1d7f_5f40),
+            (
+                "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                0xff42_329b_90e5_0d58,
+            ),
+            (
+                "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                0xc39c_ab13_b115_aad3,
+            ),
+        ];
+        for (seed, &(s, h)) in vectors.iter().enumerate() {
+            assert_eq!(wyhash(s.as_bytes(), seed as u64), h, "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn test_wyhash_lengths() {
+        let bytes = (0..128u8).collect::<Vec<_>>();
+        let hashes = (0..bytes.len())
+            .map(|n| wyhash(&bytes[..n], 0))
+            .collect::<HashSet<_>>();
+        assert_eq!(hashes.len(), bytes.len());
+    }
+
+    #[test]
+    fn test_seed() {
+        let bh1 = WyBuildHasher::with_seed(1);
+        let bh2 = WyBuildHasher::with_seed(1);
+        let bh3 = WyBuildHasher::with_seed(2);
+        assert_eq!(bh1.hash_one("apbf"), bh2.hash_one("apbf"));
+        assert_ne!(bh1.hash_one("apbf"), bh3.hash_one("apbf"));
+        assert_eq!(bh1.hash_one(42u64), bh2.hash_one(42u64));
+        assert_ne!(bh1.hash_one(42u64), bh3.hash_one(42u64));
+    }
+
+    #[test]
+    fn test_reseed_unrestorable() {
+        let hashers = KMHashers::with_build_hashers(
+            1000,
+            WyBuildHasher::with_seed(1),
+            WyBuildHasher::with_seed(2),
+        );
+        let mut apbf: APBF<u64, _> = APBF::with_hashers(4, 3, 1000, hashers);
+        apbf.reset_with_seed(42);
+        apbf.insert(0);
+        assert!(APBF::<u64, _>::from_seeded_bytes(&apbf.to_bytes()).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+use crate::builder::Builder;
+use crate::core::APBF;
+use crate::filter::ProbabilisticFilter;
+use crate::hash::km::KMHashers;
+use crate::hash::Hashers;
+
+/// APBF whose values expire after a number of insertions or a duration,
+/// whichever comes first.
+///
+/// The logical slices shift every `inserts / l` insertions rounded up
+/// as in [`APBF`], and also every `duration / l` without enough insertions.
+/// A value is held for the `l` generations following the one it was
+/// inserted in, and at most `k + l` generations, like the transition zone
+/// of [`APBF`].
+///
+/// If every generation ends for the same cause, a value is thus held at
+/// least until the corresponding limit is reached. When both causes mix,
+/// it may expire earlier: with `l = 3`, two generations ended by
+/// `inserts / 3` insertions and two ended by periods of `duration / 3`
+/// retire it after two thirds of each limit.
+///
+/// ```
+/// use std::time::Duration;
+/// use apbf::HybridAPBF;
+///
+/// // The last 1M events or the last 15 minutes.
+/// let mut apbf = HybridAPBF::new(10, 10, 1 << 20, 1_000_000, Duration::from_secs(15 * 60));
+/// apbf.insert(42);
+/// assert!(apbf.contains(42));
+/// ```
+pub struct HybridAPBF<T: ?Sized, H: Hashers> {
+    apbf: APBF<T, H>,
+    period: Duration,
+    last: Instant, // start of the current period
+}
+
+impl<T: Hash + ?Sized> HybridAPBF<T, KMHashers<RandomState, RandomState>> {
+    /// Creates a new instance with parameters `k`, `l` and `m` which holds
+    /// the last `inserts` values inserted within the last `duration`.
+    /// See [`Builder::generation`] for the effect of `inserts` on
+    /// the false positive rate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `duration / l` is zero.
+    pub fn new(k: usize, l: usize, m: usize, inserts: u64, duration: Duration) -> Self {
+        Self::from_builder(Builder::new(k, l, m), inserts, duration)
+    }
+}
+
+impl<T, H> HybridAPBF<T, H>
+where
+    T: Hash + ?Sized,
+    H: Hashers,
+{
+    /// Creates a new instance from a builder, overriding its generation.
+    /// See [`new`](HybridAPBF::new).
+    pub fn from_builder(builder: Builder<T, H>, inserts: u64, duration: Duration) -> Self {
+        let l = builder.config.l;
+        let g = inserts.div_ceil(l as u64).max(1);
+        let period = duration.div_f64(l as f64);
+        assert!(period > Duration::from_secs(0), "duration is too short");
+        HybridAPBF {
+            apbf: builder.generation(g).build(),
+            period,
+            last: Instant::now(),
+        }
+    }
+
+    // Returns the number of periods elapsed since the current one started.
+    fn elapsed(&self, now: Instant) -> u64 {
+        let elapsed = now.saturating_duration_since(self.last);
+        (elapsed.as_nanos() / self.period.as_nanos()) as u64
+    }
+
+    /// Inserts a value to the structure.
+    pub fn insert<V>(&mut self, value: V)
+    where
+        V: Borrow<T>,
+    {
+        self.insert_at(value, Instant::now());
+    }
+
+    /// Inserts a value at a given time, which must not go backward.
+    pub fn insert_at<V>(&mut self, value: V, now: Instant)
+    where
+        V: Borrow<T>,
+    {
+        let shifts = self.elapsed(now);
+        if shifts > 0 {
+            self.apbf.advance_by(shifts);
+            let elapsed = now.saturating_duration_since(self.last).as_nanos();
+            let rem = elapsed % self.period.as_nanos();
+            self.last = now - Duration::from_nanos(rem as u64);
+        }
+        let before = self.apbf.shifts;
+        self.apbf.insert(value);
+        if self.apbf.shifts != before {
+            // Shifted by the number of insertions, which starts a new period.
+            self.last = now;
+        }
+    }
+
+    /// Returns `true` if the structure holds a given value.
+    pub fn contains<V>(&self, value: V) -> bool
+    where
+        V: Borrow<T>,
+    {
+        self.contains_at(value, Instant::now())
+    }
+
+    /// Returns `true` if the structure holds a given value at a given time.
+    /// Periods elapsed since the last insertion are taken into account
+    /// without modifying the structure.
+    pub fn contains_at<V>(&self, value: V, now: Instant) -> bool
+    where
+        V: Borrow<T>,
+    {
+        let hashes = self.apbf.hashers.hash(value);
+        self.apbf.contains_after_shifts(&hashes, self.elapsed(now))
+    }
+
+    /// Returns the underlying structure.
+    /// It does not reflect the periods elapsed since the last insertion.
+    pub fn apbf(&self) -> &APBF<T, H> {
+        &self.apbf
+    }
+}
+
+impl<T, H> ProbabilisticFilter<T> for HybridAPBF<T, H>
+where
+    T: Hash + ?Sized,
+    H: Hashers,
+{
+    fn insert(&mut self, value: &T) {
+        HybridAPBF::insert(self, value)
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        HybridAPBF::contains(self, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::split::SplitHashers;
+
+    #[test]
+    fn test_expire_by_count() {
+        let mut apbf = HybridAPBF::new(4, 3, 10000, 300, Duration::from_secs(3600));
+        let now = Instant::now();
+        for v in 0..1000u64 {
+            apbf.insert_at(v, now);
+        }
+        assert!((700..1000).all(|v| apbf.contains_at(v, now)));
+        assert!(!(0..300).any(|v| apbf.contains_at(v, now)));
+    }
+
+    #[test]
+    fn test_inserts_not_multiple_of_l() {
+        let apbf = HybridAPBF::<u64, _>::new(4, 3, 10000, 301, Duration::from_secs(3600));
+        assert_eq!(apbf.apbf().generation(), 101);
+        assert!(apbf.apbf().window() >= 301);
+    }
+
+    #[test]
+    fn test_expire_by_mixed_shifts() {
+        let minute = Duration::from_secs(60);
+        let builder = Builder::new(4, 3, 10000).hashers(SplitHashers::with_seed(10000, 0));
+        let mut apbf = HybridAPBF::from_builder(builder, 300, minute * 3);
+        let start = apbf.last;
+        // Two shifts by the number of insertions.
+        for v in 0..201u64 {
+            apbf.insert_at(v, start);
+        }
+        assert_eq!(apbf.apbf().shifts, 2);
+
+        // A third shift by time keeps the value, while a fourth one retires it
+        // before either 300 insertions or 3 minutes.
+        assert!(apbf.contains_at(0, start + minute));
+        assert!(!apbf.contains_at(0, start + minute * 2));
+    }
+
+    #[test]
+    fn test_expire_by_time() {
+        let minute = Duration::from_secs(60);
+        let mut apbf = HybridAPBF::new(4, 3, 10000, 1_000_000, minute * 3);
+        let start = Instant::now();
+        for v in 0..100u64 {
+            apbf.insert_at(v, start);
+        }
+        assert!((0..100).all(|v| apbf.contains_at(v, start + minute * 2)));
+        // Expired without further insertions.
+        assert!(!(0..100).any(|v| apbf.contains_at(v, start + minute * 7)));
+
+        apbf.insert_at(100, start + minute * 7);
+        assert!(apbf.contains_at(100, start + minute * 7));
+        assert!(!(0..100).any(|v| apbf.contains_at(v, start + minute * 7)));
+    }
+}
@@ -0,0 +1,147 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::hash::Hash;
+
+use crate::analysis::Parameters;
+use crate::core::APBF;
+use crate::hash::km::KMHashers;
+use crate::hash::Hashers;
+
+/// An extension trait for iterators to drop items seen recently.
+///
+/// ```
+/// use apbf::{ApbfIteratorExt, APBF};
+///
+/// let items = vec![1, 2, 1, 3, 2];
+/// let unique: Vec<u64> = items.into_iter().dedup_recent(APBF::new(10, 7, 1024)).collect();
+/// assert_eq!(unique, [1, 2, 3]);
+/// ```
+pub trait ApbfIteratorExt: Iterator + Sized {
+    /// Returns an iterator which skips items held by `apbf`,
+    /// inserting the others as it goes. Items may be borrowed as the values
+    /// of `apbf`, e.g. `String`s deduplicated by an `APBF<str, _>`.
+    ///
+    /// Items are guaranteed to be dropped if they have been yielded within
+    /// the window of the filter. Items may also be dropped by false positives.
+    fn dedup_recent<T, H>(self, apbf: APBF<T, H>) -> DedupRecent<Self, H, T>
+    where
+        T: Hash + ?Sized,
+        H: Hashers,
+        Self::Item: Borrow<T>,
+    {
+        DedupRecent { iter: self, apbf }
+    }
+
+    /// Same as [`dedup_recent`](Self::dedup_recent) with a new filter
+    /// of given parameters.
+    fn dedup_recent_with(
+        self,
+        params: Parameters,
+    ) -> DedupRecent<Self, KMHashers<RandomState, RandomState>>
+    where
+        Self::Item: Hash,
+    {
+        let Parameters { k, l, m } = params;
+        self.dedup_recent::<Self::Item, _>(APBF::new(k, l, m))
+    }
+}
+
+impl<I: Iterator> ApbfIteratorExt for I {}
+
+/// An iterator returned by [`ApbfIteratorExt::dedup_recent`].
+pub struct DedupRecent<I: Iterator, H: Hashers, T: ?Sized = <I as Iterator>::Item> {
+    iter: I,
+    apbf: APBF<T, H>,
+}
+
+impl<I, H, T> DedupRecent<I, H, T>
+where
+    I: Iterator,
+    I::Item: Borrow<T>,
+    T: Hash + ?Sized,
+    H: Hashers,
+{
+    /// Returns the filter holding the items yielded so far.
+    pub fn apbf(&self) -> &APBF<T, H> {
+        &self.apbf
+    }
+
+    /// Consumes the iterator and returns the filter, e.g. to continue
+    /// deduplicating another batch.
+    pub fn into_apbf(self) -> APBF<T, H> {
+        self.apbf
+    }
+}
+
+impl<I, H, T> Iterator for DedupRecent<I, H, T>
+where
+    I: Iterator,
+    I::Item: Borrow<T>,
+    T: Hash + ?Sized,
+    H: Hashers,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let apbf = &mut self.apbf;
+        self.iter.find(|item| apbf.insert_if_absent(item.borrow()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // Checks the items yielded by `dedup`, which deduplicates given items
+    // with a given filter and returns the items it yields and the filter.
+    pub(crate) fn check_dedup_recent<F>(dedup: F)
+    where
+        F: FnOnce(
+            APBF<u64, KMHashers<RandomState, RandomState>>,
+            Vec<u64>,
+        ) -> (Vec<u64>, APBF<u64, KMHashers<RandomState, RandomState>>),
+    {
+        let apbf = APBF::with_generation(10, 7, 1000, 10);
+        let items = (0..20u64).chain(0..20).chain(100..300).chain(0..20);
+        let (unique, apbf) = dedup(apbf, items.collect());
+
+        // The second batch of 0..20 is within the window, while the third is not.
+        let expected: Vec<u64> = (0..20).chain(100..300).chain(0..20).collect();
+        assert_eq!(unique, expected);
+        assert!(apbf.contains(299));
+    }
+
+    #[test]
+    fn test_dedup_recent() {
+        check_dedup_recent(|apbf, items| {
+            let mut iter = items.into_iter().dedup_recent(apbf);
+            let unique = iter.by_ref().collect();
+            (unique, iter.into_apbf())
+        });
+    }
+
+    #[test]
+    fn test_dedup_recent_with() {
+        let params = Parameters {
+            k: 10,
+            l: 7,
+            m: 1024,
+        };
+        let unique = vec!["a", "b", "a", "c"]
+            .into_iter()
+            .dedup_recent_with(params);
+        assert_eq!(unique.collect::<Vec<_>>(), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_dedup_recent_borrowed() {
+        let items = vec!["a", "b", "a"].into_iter().map(String::from);
+        let apbf: APBF<str, _> = APBF::new(10, 7, 1024);
+        let unique = items.dedup_recent(apbf).collect::<Vec<_>>();
+        assert_eq!(unique, ["a", "b"]);
+    }
+}
@@ -1,4 +1,48 @@
+pub mod analysis;
+mod apqf;
+#[cfg(feature = "zerocopy")]
+pub mod archive;
+mod builder;
 mod core;
+#[cfg(feature = "eval")]
+pub mod eval;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod filter;
+mod format;
 pub mod hash;
+mod hybrid;
+mod iter;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+mod multi;
+mod rotation;
+mod scalable;
+mod slice;
+mod snapshot;
+mod stats;
+mod store;
+#[cfg(feature = "futures")]
+pub mod stream;
+mod wal;
+mod warm;
+#[cfg(feature = "wasm-bindgen")]
+pub mod wasm;
+mod writer;
 
-pub use crate::core::APBF;
+pub use crate::apqf::ApqfFilter;
+pub use crate::builder::Builder;
+pub use crate::core::{Clearing, Layout, SaturationEvent, ShiftEvent, APBF};
+pub use crate::filter::ProbabilisticFilter;
+pub use crate::hybrid::HybridAPBF;
+pub use crate::iter::{ApbfIteratorExt, DedupRecent};
+pub use crate::multi::MultiWindowAPBF;
+pub use crate::rotation::Rotation;
+pub use crate::scalable::ScalableAPBF;
+pub use crate::slice::BitSlice;
+pub use crate::snapshot::ApbfSnapshot;
+#[cfg(feature = "stats")]
+pub use crate::stats::MetricsSink;
+pub use crate::store::{BitStore, StoreState};
+pub use crate::wal::WalAPBF;
+pub use crate::writer::{ApbfReader, ApbfWriter};
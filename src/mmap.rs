@@ -0,0 +1,415 @@
+//! Persistent APBF backed by a memory-mapped file.
+//!
+//! The file consists of a header followed by the words of the bit array:
+//!
+//! | word          | content                                        |
+//! |---------------|------------------------------------------------|
+//! | 0             | magic number `APBFMMAP`                        |
+//! | 1             | format version                                 |
+//! | 2..8          | `k`, `l`, `m`, `g`, layout and clearing        |
+//! | 8             | seed of the hash functions                     |
+//! | 9..14         | counter, position, cursor, shifts and next id  |
+//! | 14            | flags; bit 0 is set if the seed is known       |
+//! | 15            | reserved                                       |
+//! | 16..16+s      | hash function index of each physical slice     |
+//! | 16+s..        | bit array                                      |
+//!
+//! All words are stored in the native byte order, so a file cannot be
+//! moved to a machine with a different endianness.
+//!
+//! [`APBF::create`] and [`APBF::open`] return an instance whose storage is
+//! a [`MmapStore`], the [`BitStore`] mapping the file.
+//!
+//! # Crash consistency
+//!
+//! Insertions write the bit array in place, while the rest of the state
+//! (the counter, the position of the logical slices and so on) is written
+//! to the header on every shift, on [`APBF::flush`] and when the instance
+//! is dropped.
+//!
+//! - If the process crashes, the bits written so far survive in the page
+//!   cache. The state in the header may be as old as the last shift, so the
+//!   current generation may last up to `g` more insertions after reopening.
+//!   No inserted value is lost.
+//! - If the system crashes, pages not yet written back may be lost in any
+//!   order. Call [`APBF::flush`] to make the whole state durable.
+//!
+//! A file must be modified by one instance at a time. Other processes may
+//! open the same file to share memory, but their view of the logical slices
+//! is only refreshed when they reopen it.
+//!
+//! # Windows larger than memory
+//!
+//! With [`Layout::Sliced`](crate::Layout::Sliced), insertions only write
+//! the `k` newest slices, and each slice occupies a contiguous range of the
+//! file, so the pages of the older slices are never dirtied and the kernel
+//! can evict them under memory pressure without writing them back.
+//!
+//! Queries do not spare the older slices: the search starts at the `l`-th
+//! logical slice, the newest of the oldest `k`, and steps `k` slices towards
+//! the newest on every miss. A query thus reads about `(k + l) / k` words at
+//! random positions spread over all slices, including the oldest ones. When
+//! the file does not fit in memory, a query-heavy workload keeps faulting
+//! pages of every slice back in, and the filter is only cheap to hold if
+//! queries are rare compared to insertions. The slices are never compressed.
+
+use std::collections::hash_map::RandomState;
+use std::fs::{File, OpenOptions};
+use std::hash::{BuildHasher, Hash};
+use std::io::{self, Read};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+use crate::analysis::Parameters;
+use crate::builder::Builder;
+use crate::core::{APBF, WORD_BITS};
+use crate::format::*;
+use crate::hash::sip::SipBuildHasher128;
+use crate::hash::split::SplitHashers;
+use crate::store::{BitStore, StoreState};
+
+/// A [`BitStore`] in a shared writable mapping of a whole file,
+/// used by [`APBF::create`] and [`APBF::open`].
+pub struct MmapStore {
+    file: File,
+    ptr: *mut u64,
+    len: usize, // number of mapped words
+    s: usize,   // number of physical slices
+    w: usize,   // number of words for each slice
+}
+
+// The mapping is owned like a `Vec<u64>`.
+unsafe impl Send for MmapStore {}
+unsafe impl Sync for MmapStore {}
+
+impl MmapStore {
+    // Maps `file` holding `s` slices of `w` words.
+    fn new(file: File, s: usize, w: usize) -> io::Result<Self> {
+        let mut store = MmapStore {
+            file,
+            ptr: ptr::null_mut(),
+            len: 0,
+            s,
+            w,
+        };
+        store.map()?;
+        Ok(store)
+    }
+
+    fn map(&mut self) -> io::Result<()> {
+        let len = HEADER_WORDS + self.s + self.s * self.w;
+        let bytes = len * WORD_BYTES;
+        if self.file.metadata()?.len() != bytes as u64 {
+            self.file.set_len(bytes as u64)?;
+        }
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                bytes,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                self.file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        self.ptr = ptr as *mut u64;
+        self.len = len;
+        Ok(())
+    }
+
+    fn unmap(&mut self) {
+        if !self.ptr.is_null() {
+            unsafe {
+                libc::munmap(self.ptr as *mut libc::c_void, self.len * WORD_BYTES);
+            }
+            self.ptr = ptr::null_mut();
+            self.len = 0;
+        }
+    }
+
+    fn all(&self) -> &[u64] {
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    fn all_mut(&mut self) -> &mut [u64] {
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+
+    fn header(&self) -> &[u64] {
+        &self.all()[..HEADER_WORDS + self.s]
+    }
+
+    fn header_mut(&mut self) -> &mut [u64] {
+        let h = HEADER_WORDS + self.s;
+        &mut self.all_mut()[..h]
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let ret = unsafe {
+            libc::msync(
+                self.ptr as *mut libc::c_void,
+                self.len * WORD_BYTES,
+                libc::MS_SYNC,
+            )
+        };
+        if ret != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl BitStore for MmapStore {
+    fn words(&self) -> &[u64] {
+        &self.all()[HEADER_WORDS + self.s..]
+    }
+
+    fn words_mut(&mut self) -> &mut [u64] {
+        let h = HEADER_WORDS + self.s;
+        &mut self.all_mut()[h..]
+    }
+
+    fn replace(&mut self, words: Vec<u64>) -> bool {
+        self.unmap();
+        self.s = words.len() / self.w;
+        self.map().expect("failed to resize the mapped file");
+        self.words_mut().copy_from_slice(&words);
+        true
+    }
+
+    // The magic number is left to `APBF::create`.
+    fn sync(&mut self, state: StoreState<'_>) {
+        let (header, ids) = self.header_mut().split_at_mut(HEADER_WORDS);
+        header[H_MAGIC + 1..].copy_from_slice(&state.header[H_MAGIC + 1..]);
+        ids.copy_from_slice(state.ids);
+    }
+}
+
+impl Drop for MmapStore {
+    fn drop(&mut self) {
+        self.unmap();
+    }
+}
+
+impl<T: Hash + ?Sized> APBF<T, SplitHashers<SipBuildHasher128>, MmapStore> {
+    /// Creates a new APBF instance whose bit array lives in the file at `path`,
+    /// truncating the file if it exists. The hash functions are seeded randomly
+    /// and the seed is stored in the file along with the parameters.
+    ///
+    /// The instance uses the default generation, [`Layout::Sliced`](crate::Layout::Sliced)
+    /// and [`Clearing::Eager`](crate::Clearing::Eager). See the [module documentation](crate::mmap)
+    /// for the file format and crash consistency.
+    pub fn create<P: AsRef<Path>>(path: P, params: Parameters) -> io::Result<Self> {
+        let Parameters { k, l, m } = params;
+        assert!(k > 0 && l > 0 && m > 0, "parameters must be positive");
+
+        let seed = RandomState::new().hash_one(0u64);
+        let hashers = SplitHashers::with_seed(m as u64, seed);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let builder = Builder::new(k, l, m).hashers(hashers);
+        let w = m.div_ceil(WORD_BITS);
+        let mut apbf = Self::try_from_builder(builder, |s, _| MmapStore::new(file, s, w))?;
+        apbf.seed = Some(seed);
+        apbf.sync();
+        // The magic number is written last so that a partially created file
+        // is never recognized.
+        apbf.bits.header_mut()[H_MAGIC] = MAGIC;
+        Ok(apbf)
+    }
+
+    /// Opens an APBF instance stored in the file at `path` by [`APBF::create`].
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if the file is
+    /// not a valid APBF file or its parameters differ from `params`.
+    pub fn open<P: AsRef<Path>>(path: P, params: Parameters) -> io::Result<Self> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+
+        let mut buf = [0u8; HEADER_WORDS * WORD_BYTES];
+        file.read_exact(&mut buf).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => invalid("file is too short".to_string()),
+            _ => e,
+        })?;
+        let mut header = [0u64; HEADER_WORDS];
+        for (h, b) in header.iter_mut().zip(buf.chunks_exact(WORD_BYTES)) {
+            let mut word = [0u8; WORD_BYTES];
+            word.copy_from_slice(b);
+            *h = u64::from_ne_bytes(word);
+        }
+
+        let header = Header::decode(&header)?;
+        let Parameters { k, l, m } = params;
+        if (header.k, header.l, header.m) != (k, l, m) {
+            return Err(invalid(format!(
+                "parameters mismatch: stored (k, l, m) = {:?}",
+                (header.k, header.l, header.m)
+            )));
+        }
+
+        let seed = header
+            .seed
+            .ok_or_else(|| invalid("the seed is unknown".to_string()))?;
+        let hashers = SplitHashers::with_seed(m as u64, seed);
+        let builder = Builder::new(k, l, m)
+            .hashers(hashers)
+            .layout(header.layout)
+            .clearing(header.clearing)
+            .generation(header.g);
+        let len = file.metadata()?.len();
+        if len != ((HEADER_WORDS + header.body_words()) * WORD_BYTES) as u64 {
+            return Err(invalid(format!("unexpected file size {}", len)));
+        }
+        let w = header.w();
+        let mut apbf = Self::try_from_builder(builder, |s, _| MmapStore::new(file, s, w))?;
+        apbf.n = header.n;
+        apbf.p = header.p;
+        apbf.cursor = header.cursor;
+        apbf.shifts = header.shifts;
+        apbf.next_id = header.next_id;
+        apbf.seed = Some(seed);
+        apbf.ids = apbf.bits.header()[HEADER_WORDS..].to_vec();
+        Ok(apbf)
+    }
+
+    /// Writes the state to the file and waits until the file is written
+    /// back to the storage.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.sync();
+        self.bits.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("apbf-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_reopen() {
+        let path = temp_path("reopen");
+        let params = Parameters {
+            k: 4,
+            l: 3,
+            m: 1000,
+        };
+        let g = {
+            let mut apbf: APBF<u64, _, MmapStore> = APBF::create(&path, params).unwrap();
+            for v in 0..100 {
+                apbf.insert(v);
+            }
+            apbf.g
+        };
+
+        let mut apbf: APBF<u64, _, MmapStore> = APBF::open(&path, params).unwrap();
+        assert_eq!(apbf.n, 100 % g);
+        assert_eq!(apbf.shifts, 100 / g);
+        for v in 0..100 {
+            assert!(apbf.contains(v));
+        }
+        apbf.insert(100);
+        apbf.flush().unwrap();
+        assert!(apbf.contains(100));
+        drop(apbf);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_reopen_full_generation() {
+        let path = temp_path("full");
+        let params = Parameters {
+            k: 4,
+            l: 3,
+            m: 1000,
+        };
+        let g = {
+            let mut apbf: APBF<u64, _, MmapStore> = APBF::create(&path, params).unwrap();
+            let g = apbf.g;
+            for v in 0..g {
+                apbf.insert(v);
+            }
+            g
+        };
+
+        let apbf: APBF<u64, _, MmapStore> = APBF::open(&path, params).unwrap();
+        assert_eq!((apbf.n, apbf.shifts), (g, 0));
+        assert!((0..g).all(|v| apbf.contains(v)));
+        drop(apbf);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_reopen_resized() {
+        let path = temp_path("resized");
+        {
+            let mut apbf: APBF<u64, _, MmapStore> = APBF::create(
+                &path,
+                Parameters {
+                    k: 4,
+                    l: 3,
+                    m: 1000,
+                },
+            )
+            .unwrap();
+            for v in 0..10 {
+                apbf.insert(v);
+            }
+            apbf.resize_window(5);
+        }
+
+        let params = Parameters {
+            k: 4,
+            l: 5,
+            m: 1000,
+        };
+        let apbf: APBF<u64, _, MmapStore> = APBF::open(&path, params).unwrap();
+        for v in 0..10 {
+            assert!(apbf.contains(v));
+        }
+        drop(apbf);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_open_invalid() {
+        let path = temp_path("invalid");
+        let params = Parameters {
+            k: 4,
+            l: 3,
+            m: 1000,
+        };
+        drop(APBF::<u64, _, MmapStore>::create(&path, params).unwrap());
+
+        let other = Parameters {
+            k: 4,
+            l: 4,
+            m: 1000,
+        };
+        let err = APBF::<u64, _, MmapStore>::open(&path, other).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        fs::write(&path, b"not an APBF file at all").unwrap();
+        let err = APBF::<u64, _, MmapStore>::open(&path, params)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
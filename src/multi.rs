@@ -0,0 +1,207 @@
+use std::borrow::Borrow;
+use std::hash::{BuildHasher, Hash};
+
+use crate::analysis::Parameters;
+use crate::core::APBF;
+use crate::filter::ProbabilisticFilter;
+use crate::hash::km::{KMHashes, Reduction};
+use crate::hash::sip::SipBuildHasher128;
+use crate::hash::split::SplitHashers;
+use crate::hash::Hasher128;
+
+/// A set of APBFs with different windows receiving the same values,
+/// such as the values seen in the last hour and in the last day.
+///
+/// Each value is hashed once into a 128-bit hash value, from which
+/// the hash functions of every window are derived as in [`SplitHashers`],
+/// so an insertion costs a single pass over the value regardless of
+/// the number of windows.
+///
+/// ```
+/// use apbf::analysis::Parameters;
+/// use apbf::MultiWindowAPBF;
+///
+/// let mut apbf = MultiWindowAPBF::new(&[
+///     Parameters { k: 10, l: 7, m: 1024 },
+///     Parameters { k: 10, l: 14, m: 4096 },
+/// ]);
+/// apbf.insert(42);
+/// assert!(apbf.contains(0, 42));
+/// assert!(apbf.contains(1, 42));
+/// ```
+pub struct MultiWindowAPBF<T, B = SipBuildHasher128>
+where
+    T: ?Sized,
+    B: BuildHasher,
+    B::Hasher: Hasher128,
+{
+    bh: B,
+    windows: Vec<APBF<T, SplitHashers<B>>>,
+}
+
+impl<T: Hash + ?Sized> MultiWindowAPBF<T> {
+    /// Creates a new instance with a window for each of `params`,
+    /// hashing with SipHash-1-3 with random keys.
+    pub fn new(params: &[Parameters]) -> Self {
+        Self::with_build_hasher(params, SipBuildHasher128::new())
+    }
+}
+
+impl<T, B> MultiWindowAPBF<T, B>
+where
+    T: Hash + ?Sized,
+    B: BuildHasher + Clone,
+    B::Hasher: Hasher128,
+{
+    /// Creates a new instance with a window for each of `params`
+    /// and a 128-bit hash function shared by all windows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `params` is empty.
+    pub fn with_build_hasher(params: &[Parameters], bh: B) -> Self {
+        assert!(!params.is_empty(), "at least one window is required");
+        let windows = params
+            .iter()
+            .map(|&Parameters { k, l, m }| {
+                let hashers = SplitHashers::with_build_hasher(m as u64, bh.clone());
+                APBF::with_hashers(k, l, m, hashers)
+            })
+            .collect();
+        MultiWindowAPBF { bh, windows }
+    }
+
+    // Returns the 128-bit hash value of a value as two halves.
+    fn hash(&self, value: &T) -> (u64, u64) {
+        let mut h = self.bh.build_hasher();
+        value.hash(&mut h);
+        h.finish128()
+    }
+
+    /// Inserts a value to all windows.
+    pub fn insert<V>(&mut self, value: V)
+    where
+        V: Borrow<T>,
+    {
+        let (x1, x2) = self.hash(value.borrow());
+        for apbf in &mut self.windows {
+            let hashes = KMHashes::new(x1, x2, apbf.m as u64, Reduction::FastRange);
+            apbf.insert_hashed(&hashes);
+        }
+    }
+
+    /// Returns `true` if the `i`-th window holds a given value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is not less than the number of windows.
+    pub fn contains<V>(&self, i: usize, value: V) -> bool
+    where
+        V: Borrow<T>,
+    {
+        let apbf = &self.windows[i];
+        let (x1, x2) = self.hash(value.borrow());
+        let hashes = KMHashes::new(x1, x2, apbf.m as u64, Reduction::FastRange);
+        apbf.contains_hashed(&hashes)
+    }
+
+    /// Returns the indices of the windows holding a given value.
+    pub fn contains_all<V>(&self, value: V) -> Vec<usize>
+    where
+        V: Borrow<T>,
+    {
+        let (x1, x2) = self.hash(value.borrow());
+        self.windows
+            .iter()
+            .enumerate()
+            .filter(|(_, apbf)| {
+                let hashes = KMHashes::new(x1, x2, apbf.m as u64, Reduction::FastRange);
+                apbf.contains_hashed(&hashes)
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Returns the number of windows.
+    pub fn len(&self) -> usize {
+        self.windows.len()
+    }
+
+    /// Returns `false`, since there is always at least one window.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Returns the `i`-th window, e.g. to inspect its parameters.
+    /// Its hash functions are the ones used by this structure.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is not less than the number of windows.
+    pub fn window(&self, i: usize) -> &APBF<T, SplitHashers<B>> {
+        &self.windows[i]
+    }
+
+    /// Returns the `i`-th window mutably, e.g. to advance its generation
+    /// from a timer of its own.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is not less than the number of windows.
+    pub fn window_mut(&mut self, i: usize) -> &mut APBF<T, SplitHashers<B>> {
+        &mut self.windows[i]
+    }
+}
+
+// A value is held if any window holds it, i.e. within the longest window.
+impl<T, B> ProbabilisticFilter<T> for MultiWindowAPBF<T, B>
+where
+    T: Hash + ?Sized,
+    B: BuildHasher + Clone,
+    B::Hasher: Hasher128,
+{
+    fn insert(&mut self, value: &T) {
+        MultiWindowAPBF::insert(self, value)
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        let (x1, x2) = self.hash(value);
+        self.windows.iter().any(|apbf| {
+            let hashes = KMHashes::new(x1, x2, apbf.m as u64, Reduction::FastRange);
+            apbf.contains_hashed(&hashes)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multi_window() {
+        let mut apbf = MultiWindowAPBF::new(&[
+            Parameters {
+                k: 4,
+                l: 3,
+                m: 1000,
+            },
+            Parameters {
+                k: 4,
+                l: 12,
+                m: 1000,
+            },
+        ]);
+        let (short, long) = (apbf.window(0).window(), apbf.window(1).window());
+        for v in 0..long {
+            apbf.insert(v);
+        }
+        for v in long - short..long {
+            assert_eq!(apbf.contains_all(v), [0, 1]);
+        }
+        for v in 0..long {
+            assert!(apbf.contains(1, v));
+        }
+        // The values are shared with the windows themselves.
+        assert!(apbf.window(1).contains(0));
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// A handle to a background thread calling a function on a fixed schedule,
+/// typically to advance the generations of a filter shared with a lock
+/// so that values expire on time even when no insertions arrive.
+///
+/// The thread stops when the handle is dropped.
+///
+/// ```
+/// use std::sync::{Arc, Mutex};
+/// use std::time::Duration;
+/// use apbf::{Rotation, APBF};
+///
+/// // Generations are only advanced by the rotation.
+/// let apbf = Arc::new(Mutex::new(APBF::<u64, _>::with_generation(10, 7, 1024, u64::MAX)));
+/// let rotation = {
+///     let apbf = Arc::clone(&apbf);
+///     Rotation::spawn(Duration::from_secs(60), move || apbf.lock().unwrap().advance())
+/// };
+/// apbf.lock().unwrap().insert(42);
+/// rotation.stop();
+/// ```
+pub struct Rotation {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Rotation {
+    /// Spawns a thread calling `f` every `period`.
+    ///
+    /// Calls are scheduled at fixed times from the start, so a slow call
+    /// delays the next one without shifting the rest of the schedule.
+    pub fn spawn<F>(period: Duration, mut f: F) -> Self
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            let mut next = Instant::now() + period;
+            loop {
+                let timeout = next.saturating_duration_since(Instant::now());
+                match rx.recv_timeout(timeout) {
+                    Err(RecvTimeoutError::Timeout) => {
+                        f();
+                        next += period;
+                    }
+                    _ => return,
+                }
+            }
+        });
+        Rotation {
+            stop: Some(tx),
+            thread: Some(thread),
+        }
+    }
+
+    /// Stops the thread, waiting for a call in progress to complete.
+    pub fn stop(self) {}
+}
+
+impl Drop for Rotation {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            // A panic in the function has already been reported by the thread.
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::APBF;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_rotation() {
+        let (k, l) = (4, 3);
+        let apbf = Arc::new(Mutex::new(APBF::with_generation(k, l, 1000, u64::MAX)));
+        apbf.lock().unwrap().insert(42u64);
+
+        let rotation = {
+            let apbf = Arc::clone(&apbf);
+            Rotation::spawn(Duration::from_millis(1), move || {
+                apbf.lock().unwrap().advance()
+            })
+        };
+        while apbf.lock().unwrap().contains(42) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        rotation.stop();
+
+        let shifts = apbf.lock().unwrap().shifts;
+        assert!(shifts >= (l + 1) as u64);
+        thread::sleep(Duration::from_millis(10));
+        assert_eq!(apbf.lock().unwrap().shifts, shifts);
+    }
+}
@@ -0,0 +1,218 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::hash::Hash;
+use std::mem;
+use std::sync::Arc;
+
+use crate::builder::Builder;
+use crate::core::APBF;
+use crate::hash::km::KMHashers;
+use crate::hash::Hashers;
+
+/// Scalable Age-Partitioned Bloom Filter, which grows when more values are
+/// inserted in a generation than it was sized for.
+///
+/// Unlike [`APBF`], it does not shift logical slices on insertions.
+/// Generations are advanced only by [`advance`](Self::advance),
+/// e.g. from a timer, so the number of insertions in a generation is unbounded.
+///
+/// It consists of a chain of APBFs (stages), all of which are advanced together.
+/// Values are inserted into the newest stage. Once a slice of the newest stage
+/// gets half full, which is the fill ratio an APBF is designed for,
+/// a new stage with `growth` times larger slices and one more slice to fill
+/// for each insertion is added. The additional slice roughly halves the false
+/// positive rate of each new stage, so the total rate stays below about twice
+/// the rate of the first one. Stages not receiving insertions become empty
+/// after `k + l` generations, and are dropped.
+pub struct ScalableAPBF<T: ?Sized, H: Hashers> {
+    stages: Vec<Stage<T, H>>, // the newest one comes last
+    make_hashers: Arc<dyn Fn(u64) -> H + Send + Sync>,
+    l: usize,
+    growth: usize,
+    inserts: usize, // number of insertions since the last check of saturation
+}
+
+struct Stage<T: ?Sized, H: Hashers> {
+    apbf: APBF<T, H>,
+    idle: u64, // number of generations since it stopped receiving insertions
+}
+
+impl<T: Hash + ?Sized> ScalableAPBF<T, KMHashers<RandomState, RandomState>> {
+    /// Creates a new instance whose first stage has the given parameters.
+    /// See [`APBF`] for the meaning of `k`, `l` and `m`.
+    pub fn new(k: usize, l: usize, m: usize) -> Self {
+        Self::with_hashers(k, l, m, KMHashers::new)
+    }
+}
+
+impl<T, H> ScalableAPBF<T, H>
+where
+    T: Hash + ?Sized,
+    H: Hashers,
+{
+    /// Creates a new instance using `make_hashers` to create hash functions
+    /// returning values in `[0, m)` for each stage with `m` bits per slice.
+    pub fn with_hashers<F>(k: usize, l: usize, m: usize, make_hashers: F) -> Self
+    where
+        F: Fn(u64) -> H + Send + Sync + 'static,
+    {
+        let mut apbf = ScalableAPBF {
+            stages: Vec::new(),
+            make_hashers: Arc::new(make_hashers),
+            l,
+            growth: 2,
+            inserts: 0,
+        };
+        apbf.push_stage(k, m);
+        apbf
+    }
+
+    /// Sets the growth factor of the slice size for each new stage.
+    /// Defaults to 2.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `growth` is zero.
+    pub fn growth(mut self, growth: usize) -> Self {
+        assert!(growth > 0, "growth must be positive");
+        self.growth = growth;
+        self
+    }
+
+    fn push_stage(&mut self, k: usize, m: usize) {
+        let hashers = (self.make_hashers)(m as u64);
+        let apbf = Builder::new(k, self.l, m)
+            .hashers(hashers)
+            .generation(u64::MAX)
+            .build();
+        self.stages.push(Stage { apbf, idle: 0 });
+        self.inserts = 0;
+    }
+
+    fn newest(&self) -> &APBF<T, H> {
+        &self.stages.last().unwrap().apbf
+    }
+
+    // Adds a new stage if the newest one is saturated.
+    fn check_saturation(&mut self) {
+        let newest = self.newest();
+        let (k, m) = (newest.k(), newest.m());
+
+        // Counting bits takes time proportional to m, so it is done
+        // every m / 64 insertions.
+        if self.inserts < std::cmp::max(m / 64, 1) {
+            return;
+        }
+
+        // The (k - 1)-th slice has received the most insertions
+        // among the slices which are still receiving them.
+        if newest.fill_ratio(k - 1) >= 0.5 {
+            self.push_stage(k + 1, m * self.growth);
+        } else {
+            self.inserts = 0;
+        }
+    }
+
+    /// Inserts a value to the structure.
+    pub fn insert<V>(&mut self, value: V)
+    where
+        V: Borrow<T>,
+    {
+        self.stages.last_mut().unwrap().apbf.insert(value);
+        self.inserts += 1;
+        self.check_saturation();
+    }
+
+    /// Returns `true` if the structure holds a given value.
+    pub fn contains<V>(&self, value: V) -> bool
+    where
+        V: Borrow<T>,
+    {
+        let value = value.borrow();
+        self.stages.iter().any(|s| s.apbf.contains(value))
+    }
+
+    /// Returns the number of bytes taken by the structure and its stages.
+    /// See [`APBF::memory_usage`].
+    pub fn memory_usage(&self) -> usize {
+        // Stages hold the structures inline.
+        let heap = self
+            .stages
+            .iter()
+            .map(|s| s.apbf.memory_usage() - mem::size_of::<APBF<T, H>>())
+            .sum::<usize>();
+        mem::size_of::<Self>() + self.stages.capacity() * mem::size_of::<Stage<T, H>>() + heap
+    }
+
+    /// Starts a new generation in all stages.
+    pub fn advance(&mut self) {
+        let n = self.stages.len();
+        for (i, stage) in self.stages.iter_mut().enumerate() {
+            stage.apbf.advance();
+            if i + 1 < n {
+                stage.idle += 1;
+            }
+        }
+
+        // Drop stages which have become empty.
+        self.stages
+            .retain(|s| s.idle < (s.apbf.k() + s.apbf.l()) as u64);
+    }
+
+    /// Returns the number of stages.
+    pub fn stages(&self) -> usize {
+        self.stages.len()
+    }
+
+    /// Returns width of the sliding window in generations.
+    pub fn window(&self) -> usize {
+        self.l
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_grow() {
+        let k = 10;
+        let l = 7;
+        let m = 1024;
+        let g = analysis::generation(k, m);
+        let mut apbf = ScalableAPBF::new(k, l, m);
+        let initial = apbf.memory_usage();
+        let mut rng = StdRng::from_seed([0u8; 32]);
+
+        // Insert 10 times more values than the first stage is sized for.
+        let mut values = Vec::new();
+        for _ in 0..l {
+            for _ in 0..(g * 10) {
+                let v = rng.gen::<u64>();
+                apbf.insert(v);
+                values.push(v);
+            }
+            apbf.advance();
+        }
+        assert!(apbf.stages() > 1);
+        assert!(apbf.memory_usage() > initial * 2);
+        assert!(values.iter().all(|&v| apbf.contains(v)));
+
+        let queries = 100_000;
+        let fp = (0..queries)
+            .filter(|_| apbf.contains(rng.gen::<u64>()))
+            .count();
+        let measured = fp as f64 / queries as f64;
+        let bound = analysis::false_positive_rate(k, l, m) * 3.0;
+        assert!(measured < bound, "measured {}, bound {}", measured, bound);
+
+        // Stages are dropped without insertions.
+        for _ in 0..(k + l + apbf.stages()) {
+            apbf.advance();
+        }
+        assert_eq!(apbf.stages(), 1);
+        assert!(!values.iter().any(|&v| apbf.contains(v)));
+    }
+}
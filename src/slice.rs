@@ -0,0 +1,141 @@
+use std::hash::Hash;
+
+use crate::core::{Layout, APBF, WORD_BITS};
+use crate::hash::Hashers;
+use crate::store::BitStore;
+
+/// A read-only view of a logical slice of an [`APBF`] returned by
+/// [`APBF::slices`].
+///
+/// The bits are numbered from the first word of the slice, so that bit `h`
+/// is bit `h % 64` of word `h / 64`. With [`Layout::Sliced`], it is the bit
+/// set for a value whose hash function for the slice returns `h`.
+#[derive(Clone, Copy, Debug)]
+pub struct BitSlice<'a> {
+    words: &'a [u64],
+    start: usize, // index of the first word in `words`
+    step: usize,  // distance between consecutive words in `words`
+    w: usize,
+    m: usize,
+    age: usize,
+}
+
+impl<'a> BitSlice<'a> {
+    /// Returns the index of the slice in logical order, which is the number
+    /// of shifts since it started receiving insertions.
+    pub fn age(&self) -> usize {
+        self.age
+    }
+
+    /// Returns the number of bits in the slice.
+    pub fn len(&self) -> usize {
+        self.m
+    }
+
+    /// Returns `true` if the slice has no bits.
+    pub fn is_empty(&self) -> bool {
+        self.m == 0
+    }
+
+    /// Returns the h-th bit of the slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `h` is not less than the length of the slice.
+    pub fn get(&self, h: usize) -> bool {
+        assert!(h < self.m, "bit index out of range: {}", h);
+        let word = self.words[self.start + h / WORD_BITS * self.step];
+        word & (1 << (h % WORD_BITS)) != 0
+    }
+
+    /// Returns an iterator over the words of the slice in order.
+    /// Bits beyond the length of the slice in the last word are zero.
+    pub fn words(&self) -> impl Iterator<Item = u64> + 'a {
+        let (words, start, step) = (self.words, self.start, self.step);
+        (0..self.w).map(move |j| words[start + j * step])
+    }
+
+    /// Returns the number of bits set in the slice.
+    pub fn count_ones(&self) -> usize {
+        self.words().map(|w| w.count_ones() as usize).sum()
+    }
+}
+
+impl<T: Hash + ?Sized, H: Hashers, S: BitStore> APBF<T, H, S> {
+    /// Returns the words of the underlying bit array, including a spare slice
+    /// with [`Clearing::Amortized`](crate::Clearing::Amortized).
+    ///
+    /// The order of the slices in the array rotates on every shift, and
+    /// their words are interleaved with [`Layout::Blocked`]. Use
+    /// [`slices`](Self::slices) to access them in logical order.
+    pub fn raw_bits(&self) -> &[u64] {
+        self.bits.words()
+    }
+
+    /// Returns an iterator over the `k + l` logical slices from the newest
+    /// to the oldest.
+    ///
+    /// ```
+    /// use apbf::APBF;
+    ///
+    /// let mut apbf: APBF<u64, _> = APBF::new(10, 7, 1024);
+    /// apbf.insert(42);
+    /// let ones = apbf.slices().map(|s| s.count_ones()).collect::<Vec<_>>();
+    /// assert_eq!(ones, [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0]);
+    /// ```
+    pub fn slices(&self) -> impl Iterator<Item = BitSlice<'_>> {
+        (0..self.k + self.l).map(move |age| {
+            let i = self.physical(age);
+            let (start, step) = match self.layout {
+                Layout::Sliced => (self.slice_offset(i), 1),
+                Layout::Blocked => (i, self.s),
+            };
+            BitSlice {
+                words: self.bits.words(),
+                start,
+                step,
+                w: self.w,
+                m: self.m,
+                age,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hash::split::SplitHashers;
+    use crate::hash::{Hashers, Hashes};
+    use crate::{Builder, Layout, APBF};
+
+    #[test]
+    fn test_slices() {
+        for &layout in &[Layout::Sliced, Layout::Blocked] {
+            let mut apbf: APBF<u64, _> = Builder::new(4, 3, 1024)
+                .hashers(SplitHashers::with_seed(1024, 0))
+                .layout(layout)
+                .build();
+            apbf.insert(1);
+            apbf.advance();
+            apbf.insert(2);
+
+            let ones = apbf.slices().map(|s| s.count_ones()).collect::<Vec<_>>();
+            assert_eq!(ones, [1, 2, 2, 2, 1, 0, 0]);
+            let total: usize = apbf
+                .raw_bits()
+                .iter()
+                .map(|w| w.count_ones() as usize)
+                .sum();
+            assert_eq!(total, 8);
+
+            let hashes = apbf.hashers.hash::<u64, _>(2);
+            for s in apbf.slices().take(4) {
+                assert_eq!(s.words().count(), 1024 / 64);
+                if layout == Layout::Sliced {
+                    let h = hashes.get(apbf.ids[apbf.physical(s.age())]) as usize;
+                    assert!(s.get(h));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use crate::core::APBF;
+use crate::hash::Hashers;
+use crate::store::BitStore;
+
+/// A read-only point-in-time view of an [`APBF`] returned by [`APBF::snapshot`].
+///
+/// It owns a frozen copy of the bit array, so the original structure can
+/// keep receiving insertions while other threads query the snapshot,
+/// e.g. by sharing it through an `Arc`.
+pub struct ApbfSnapshot<T: ?Sized, H: Hashers> {
+    apbf: APBF<T, H>,
+}
+
+impl<T, H, S> APBF<T, H, S>
+where
+    T: Hash + ?Sized,
+    H: Hashers + Clone,
+    S: BitStore,
+{
+    /// Returns a snapshot of the current state which answers queries
+    /// as the structure would at this point, regardless of later insertions.
+    ///
+    /// It copies the underlying bit array, taking time and memory
+    /// proportional to `(k + l) * m`.
+    pub fn snapshot(&self) -> ApbfSnapshot<T, H> {
+        ApbfSnapshot {
+            apbf: APBF {
+                hashers: self.hashers.clone(),
+                bits: self.bits.words().to_vec(),
+                k: self.k,
+                l: self.l,
+                m: self.m,
+                w: self.w,
+                w_log2: self.w_log2,
+                layout: self.layout,
+                clearing: self.clearing,
+                s: self.s,
+                ids: self.ids.clone(),
+                next_id: self.next_id,
+                step: self.step,
+                n: self.n,
+                p: self.p,
+                g: self.g,
+                cursor: self.cursor,
+                shifts: self.shifts,
+                seed: self.seed,
+                stats: self.stats.clone(),
+                on_shift: None,
+                on_saturation: None,
+                _t: PhantomData,
+            },
+        }
+    }
+}
+
+impl<T, H> ApbfSnapshot<T, H>
+where
+    T: Hash + ?Sized,
+    H: Hashers,
+{
+    /// Returns `true` if the structure held a given value when the snapshot
+    /// was taken. See [`APBF::contains`].
+    pub fn contains<V>(&self, value: V) -> bool
+    where
+        V: Borrow<T>,
+    {
+        self.apbf.contains(value)
+    }
+
+    /// See [`APBF::contains_count`].
+    pub fn contains_count<V>(&self, value: V) -> usize
+    where
+        V: Borrow<T>,
+    {
+        self.apbf.contains_count(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_snapshot_frozen() {
+        let (k, l) = (4, 3);
+        let mut apbf: APBF<u64, _> = APBF::with_generation(k, l, 1000, 10);
+        for v in 0..10 {
+            apbf.insert(v);
+        }
+        let snapshot = apbf.snapshot();
+
+        // Retire the values inserted before the snapshot.
+        for v in 10..10 * (k + l + 1) as u64 {
+            apbf.insert(v);
+        }
+        for v in 0..10 {
+            assert!(!apbf.contains(v));
+            assert!(snapshot.contains(v));
+        }
+    }
+
+    #[test]
+    fn test_snapshot_shared() {
+        let mut apbf: APBF<u64, _> = APBF::new(10, 7, 10000);
+        for v in 0..100 {
+            apbf.insert(v);
+        }
+        let snapshot = Arc::new(apbf.snapshot());
+
+        let reader = {
+            let snapshot = Arc::clone(&snapshot);
+            thread::spawn(move || (0..100).all(|v| snapshot.contains(v)))
+        };
+        for v in 100..1000 {
+            apbf.insert(v);
+        }
+        assert!(reader.join().unwrap());
+    }
+
+    #[test]
+    fn test_snapshot_unsized() {
+        let mut apbf: APBF<[u8], _> = APBF::new(4, 3, 1000);
+        apbf.insert(&b"apbf"[..]);
+        assert!(apbf.snapshot().contains(&b"apbf"[..]));
+    }
+}
@@ -0,0 +1,200 @@
+// Lifetime counters of an APBF, only maintained with the `stats` feature
+// and empty otherwise.
+#[cfg(feature = "stats")]
+use std::hash::Hash;
+#[cfg(feature = "stats")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[cfg(feature = "stats")]
+use crate::core::APBF;
+#[cfg(feature = "stats")]
+use crate::hash::Hashers;
+#[cfg(feature = "stats")]
+use crate::store::BitStore;
+
+#[derive(Debug, Default)]
+pub(crate) struct Stats {
+    #[cfg(feature = "stats")]
+    inserts: u64,
+    #[cfg(feature = "stats")]
+    shifts: u64,
+    // Queries take `&self`, possibly from multiple threads.
+    #[cfg(feature = "stats")]
+    queries: AtomicU64,
+    #[cfg(feature = "stats")]
+    hits: AtomicU64,
+}
+
+impl Stats {
+    #[inline]
+    pub(crate) fn record_insert(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.inserts += 1;
+        }
+    }
+
+    #[inline]
+    pub(crate) fn record_shifts(&mut self, n: u64) {
+        #[cfg(feature = "stats")]
+        {
+            self.shifts += n;
+        }
+        #[cfg(not(feature = "stats"))]
+        let _ = n;
+    }
+
+    #[inline]
+    pub(crate) fn record_query(&self, hit: bool) {
+        #[cfg(feature = "stats")]
+        {
+            self.queries.fetch_add(1, Ordering::Relaxed);
+            self.hits.fetch_add(hit as u64, Ordering::Relaxed);
+        }
+        #[cfg(not(feature = "stats"))]
+        let _ = hit;
+    }
+}
+
+#[cfg(feature = "stats")]
+impl Stats {
+    pub(crate) fn inserts(&self) -> u64 {
+        self.inserts
+    }
+
+    pub(crate) fn shifts(&self) -> u64 {
+        self.shifts
+    }
+
+    pub(crate) fn queries(&self) -> u64 {
+        self.queries.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+}
+
+impl Clone for Stats {
+    fn clone(&self) -> Self {
+        Stats {
+            #[cfg(feature = "stats")]
+            inserts: self.inserts,
+            #[cfg(feature = "stats")]
+            shifts: self.shifts,
+            #[cfg(feature = "stats")]
+            queries: AtomicU64::new(self.queries()),
+            #[cfg(feature = "stats")]
+            hits: AtomicU64::new(self.hits()),
+        }
+    }
+}
+
+/// A destination of the metrics reported by [`APBF::report_metrics`].
+///
+/// The crate does not depend on any metrics library; an adapter to the
+/// `metrics` facade is a few lines:
+///
+/// ```ignore
+/// struct Facade;
+///
+/// impl apbf::MetricsSink for Facade {
+///     fn counter(&mut self, name: &'static str, value: u64) {
+///         metrics::counter!(name).absolute(value);
+///     }
+///
+///     fn gauge(&mut self, name: &'static str, value: f64) {
+///         metrics::gauge!(name).set(value);
+///     }
+/// }
+/// ```
+#[cfg(feature = "stats")]
+pub trait MetricsSink {
+    /// Records the current value of a counter, which never decreases.
+    fn counter(&mut self, name: &'static str, value: u64);
+
+    /// Records the current value of a gauge.
+    fn gauge(&mut self, name: &'static str, value: f64);
+}
+
+#[cfg(feature = "stats")]
+impl<T, H, S> APBF<T, H, S>
+where
+    T: Hash + ?Sized,
+    H: Hashers,
+    S: BitStore,
+{
+    /// Reports the lifetime counters and the health of the structure to
+    /// `sink`, e.g. from the handler of a scrape or a timer:
+    ///
+    /// - `apbf_inserts_total`, `apbf_shifts_total`, `apbf_queries_total`
+    ///   and `apbf_query_hits_total` as counters,
+    /// - `apbf_fill_ratio` of the newest slice which no longer receives
+    ///   insertions and `apbf_estimated_false_positive_rate` as gauges.
+    pub fn report_metrics<M: MetricsSink>(&self, sink: &mut M) {
+        sink.counter("apbf_inserts_total", self.total_inserts());
+        sink.counter("apbf_shifts_total", self.total_shifts());
+        sink.counter("apbf_queries_total", self.queries());
+        sink.counter("apbf_query_hits_total", self.query_hits());
+        sink.gauge("apbf_fill_ratio", self.fill_ratio(self.k()));
+        sink.gauge(
+            "apbf_estimated_false_positive_rate",
+            self.estimated_false_positive_rate(),
+        );
+    }
+}
+
+#[cfg(all(test, feature = "stats"))]
+mod tests {
+    use super::*;
+    use crate::hash::split::SplitHashers;
+    use crate::Builder;
+
+    #[derive(Default)]
+    struct Recorder {
+        counters: Vec<(&'static str, u64)>,
+        gauges: Vec<(&'static str, f64)>,
+    }
+
+    impl MetricsSink for Recorder {
+        fn counter(&mut self, name: &'static str, value: u64) {
+            self.counters.push((name, value));
+        }
+
+        fn gauge(&mut self, name: &'static str, value: f64) {
+            self.gauges.push((name, value));
+        }
+    }
+
+    #[test]
+    fn test_report_metrics() {
+        let mut apbf: APBF<u64, _> = Builder::new(4, 3, 1000)
+            .hashers(SplitHashers::with_seed(1000, 0))
+            .generation(10)
+            .build();
+        for v in 0..25 {
+            apbf.insert(v);
+        }
+        assert!(apbf.contains(0));
+        assert!(!apbf.contains(1000));
+
+        let mut recorder = Recorder::default();
+        apbf.report_metrics(&mut recorder);
+        assert_eq!(
+            recorder.counters,
+            [
+                ("apbf_inserts_total", 25),
+                ("apbf_shifts_total", 2),
+                ("apbf_queries_total", 2),
+                ("apbf_query_hits_total", 1),
+            ]
+        );
+        let names: Vec<_> = recorder.gauges.iter().map(|g| g.0).collect();
+        assert_eq!(
+            names,
+            ["apbf_fill_ratio", "apbf_estimated_false_positive_rate"]
+        );
+        assert_eq!(recorder.gauges[0].1, apbf.fill_ratio(4));
+        assert!(recorder.gauges[0].1 > 0.0);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::format::HEADER_WORDS;
+
+/// Storage of the bit array of an [`APBF`](crate::APBF) as 64-bit words.
+///
+/// It is implemented for `Vec<u64>`, which is the default, `Box<[u64]>`
+/// and `&mut [u64]`, so that the structure can be placed in an arena,
+/// shared memory or a pre-allocated buffer with
+/// [`Builder::build_with_store`](crate::Builder::build_with_store).
+pub trait BitStore {
+    /// Returns the words.
+    fn words(&self) -> &[u64];
+
+    /// Returns the words mutably.
+    fn words_mut(&mut self) -> &mut [u64];
+
+    /// Replaces the words with `words`, whose length may differ,
+    /// as required by [`APBF::resize_window`](crate::APBF::resize_window).
+    /// Returns `false` if the store cannot change its length,
+    /// which is the default.
+    fn replace(&mut self, words: Vec<u64>) -> bool {
+        let _ = words;
+        false
+    }
+
+    /// Receives the state of the structure besides the bit array whenever
+    /// it changes at a shift, as well as when the structure is dropped.
+    ///
+    /// It is used by the stores of this crate persisting the structure,
+    /// and does nothing by default.
+    fn sync(&mut self, state: StoreState<'_>) {
+        let _ = state;
+    }
+}
+
+/// The state of an [`APBF`](crate::APBF) besides the bit array, passed to
+/// [`BitStore::sync`]. Its encoding is private to the crate.
+#[cfg_attr(not(feature = "mmap"), allow(dead_code))]
+pub struct StoreState<'a> {
+    pub(crate) header: [u64; HEADER_WORDS],
+    pub(crate) ids: &'a [u64],
+}
+
+impl BitStore for Vec<u64> {
+    fn words(&self) -> &[u64] {
+        self
+    }
+
+    fn words_mut(&mut self) -> &mut [u64] {
+        self
+    }
+
+    fn replace(&mut self, words: Vec<u64>) -> bool {
+        *self = words;
+        true
+    }
+}
+
+impl BitStore for Box<[u64]> {
+    fn words(&self) -> &[u64] {
+        self
+    }
+
+    fn words_mut(&mut self) -> &mut [u64] {
+        self
+    }
+
+    fn replace(&mut self, words: Vec<u64>) -> bool {
+        *self = words.into_boxed_slice();
+        true
+    }
+}
+
+impl BitStore for &mut [u64] {
+    fn words(&self) -> &[u64] {
+        self
+    }
+
+    fn words_mut(&mut self) -> &mut [u64] {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Builder, Clearing, APBF};
+
+    #[test]
+    fn test_store_boxed() {
+        let builder = Builder::new(4, 3, 1000).clearing(Clearing::Amortized);
+        let store = vec![!0; builder.store_len()].into_boxed_slice();
+        let mut apbf: APBF<u64, _, Box<[u64]>> = builder.build_with_store(store);
+        assert!(!apbf.contains(0));
+        for v in 0..100 {
+            apbf.insert(v);
+        }
+        for v in 0..100 {
+            assert!(apbf.contains(v));
+        }
+        apbf.resize_window(5);
+        assert_eq!(apbf.l(), 5);
+    }
+
+    #[test]
+    fn test_store_borrowed() {
+        let builder = Builder::new(4, 3, 1000);
+        let mut buf = vec![0; builder.store_len()];
+        {
+            let mut apbf: APBF<u64, _, &mut [u64]> = builder.build_with_store(&mut buf[..]);
+            apbf.insert(42);
+            assert!(apbf.contains(42));
+        }
+        assert!(buf.iter().any(|&w| w != 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "the store cannot change its length")]
+    fn test_store_borrowed_resize() {
+        let builder = Builder::new(4, 3, 1000);
+        let mut buf = vec![0; builder.store_len()];
+        let mut apbf: APBF<u64, _, &mut [u64]> = builder.build_with_store(&mut buf[..]);
+        apbf.resize_window(5);
+    }
+}
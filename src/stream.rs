@@ -0,0 +1,156 @@
+//! Deduplication of asynchronous streams of [`futures_core::Stream`].
+//!
+//! ```
+//! use apbf::stream::ApbfStreamExt;
+//! use apbf::APBF;
+//! # use std::pin::Pin;
+//! # use std::task::{Context, Poll};
+//! # struct Iter<I>(I);
+//! # impl<I: Iterator + Unpin> futures_core::Stream for Iter<I> {
+//! #     type Item = I::Item;
+//! #     fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<I::Item>> {
+//! #         Poll::Ready(self.0.next())
+//! #     }
+//! # }
+//! # let events = Iter(vec![1u64, 2, 1, 3].into_iter());
+//!
+//! // `events` is a stream of `u64`, e.g. of messages consumed from a queue.
+//! let unique = events.dedup_recent(APBF::new(10, 7, 1024));
+//! ```
+
+use std::hash::Hash;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use crate::core::APBF;
+use crate::hash::Hashers;
+
+/// An extension trait for streams to drop items seen recently.
+/// See [`ApbfIteratorExt`](crate::ApbfIteratorExt) for iterators.
+pub trait ApbfStreamExt: Stream + Sized
+where
+    Self::Item: Hash,
+{
+    /// Returns a stream which skips items held by `apbf`,
+    /// like [`ApbfIteratorExt::dedup_recent`](crate::ApbfIteratorExt::dedup_recent).
+    fn dedup_recent<H: Hashers>(self, apbf: APBF<Self::Item, H>) -> DedupRecent<Self, H> {
+        DedupRecent { stream: self, apbf }
+    }
+}
+
+impl<S> ApbfStreamExt for S
+where
+    S: Stream,
+    S::Item: Hash,
+{
+}
+
+/// A stream returned by [`ApbfStreamExt::dedup_recent`],
+/// the counterpart of [`DedupRecent`](crate::DedupRecent) for iterators.
+pub struct DedupRecent<S: Stream, H: Hashers> {
+    stream: S,
+    apbf: APBF<S::Item, H>,
+}
+
+impl<S, H> DedupRecent<S, H>
+where
+    S: Stream,
+    S::Item: Hash,
+    H: Hashers,
+{
+    /// Returns the filter holding the items yielded so far.
+    pub fn apbf(&self) -> &APBF<S::Item, H> {
+        &self.apbf
+    }
+
+    /// Consumes the stream and returns the filter, e.g. to continue
+    /// deduplicating another stream.
+    pub fn into_apbf(self) -> APBF<S::Item, H> {
+        self.apbf
+    }
+}
+
+impl<S, H> Stream for DedupRecent<S, H>
+where
+    S: Stream,
+    S::Item: Hash,
+    H: Hashers,
+{
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        // SAFETY: `stream` is pinned along with `self` and never moved out,
+        // while `apbf` is not pinned. `DedupRecent` implements neither `Drop`
+        // nor `Unpin` by hand.
+        let this = unsafe { self.get_unchecked_mut() };
+        let mut stream = unsafe { Pin::new_unchecked(&mut this.stream) };
+        loop {
+            match stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    if this.apbf.insert_if_absent(&item) {
+                        return Poll::Ready(Some(item));
+                    }
+                }
+                other => return other,
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.stream.size_hint().1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iter::tests::check_dedup_recent;
+    use std::task::Waker;
+
+    // A stream over an iterator, yielding `Pending` before each item.
+    struct Iter<I> {
+        iter: I,
+        ready: bool,
+    }
+
+    impl<I: Iterator + Unpin> Stream for Iter<I> {
+        type Item = I::Item;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<I::Item>> {
+            self.ready = !self.ready;
+            if self.ready {
+                Poll::Ready(self.iter.next())
+            } else {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn test_dedup_recent() {
+        check_dedup_recent(|apbf, items| {
+            let n = items.len();
+            let iter = Iter {
+                iter: items.into_iter(),
+                ready: true,
+            };
+            let mut stream = iter.dedup_recent(apbf);
+
+            let mut cx = Context::from_waker(Waker::noop());
+            let mut unique = Vec::new();
+            let mut pending = 0;
+            loop {
+                match Pin::new(&mut stream).poll_next(&mut cx) {
+                    Poll::Ready(Some(item)) => unique.push(item),
+                    Poll::Ready(None) => break,
+                    Poll::Pending => pending += 1,
+                }
+            }
+            assert_eq!(pending, n + 1);
+            (unique, stream.into_apbf())
+        });
+    }
+}
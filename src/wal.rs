@@ -0,0 +1,269 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::io::{self, Read, Write};
+
+use crate::core::APBF;
+use crate::hash::HashPairs;
+use crate::store::BitStore;
+
+// Tags of the records in a log.
+const INSERT: u8 = 0;
+const SHIFT: u8 = 1;
+
+/// APBF recording every change to a log, from which [`APBF::replay`]
+/// rebuilds it after a crash without persisting the bit array.
+///
+/// The log is split into segments, one for each generation, written to
+/// the sinks returned by a function given the generation number, i.e.
+/// the number of shifts so far. Every segment except the first one starts
+/// with a shift to its generation. Since the values inserted `k + l`
+/// generations ago have been retired, the segments of generations up to
+/// `g - k - l` can be removed once generation `g` starts, which keeps the log
+/// at about the size of the window.
+///
+/// An insertion appends 17 bytes: a tag and the pair of hash values of
+/// the value. A shift appends 9 bytes: a tag and the generation number.
+/// The hash functions must be reproducible, such as ones created from a seed
+/// or a key, and the structure to replay into must be created with the same
+/// parameters and hash functions.
+///
+/// ```
+/// use apbf::hash::split::SplitHashers;
+/// use apbf::{Builder, WalAPBF, APBF};
+///
+/// let hashers = SplitHashers::with_seed(1024, 42);
+/// let make = || Builder::new(10, 7, 1024).hashers(hashers.clone()).build();
+///
+/// let mut segments = Vec::new();
+/// let mut wal: WalAPBF<u64, _, Vec<u8>, _> =
+///     WalAPBF::new(make(), |_| Ok(Vec::new())).unwrap();
+/// wal.insert(42).unwrap();
+/// segments.push(wal.into_sink());
+///
+/// let mut apbf: APBF<u64, _> = make();
+/// for segment in &segments {
+///     apbf.replay(&segment[..]).unwrap();
+/// }
+/// assert!(apbf.contains(42));
+/// ```
+pub struct WalAPBF<T, H, W, F>
+where
+    T: ?Sized,
+    H: HashPairs,
+    W: Write,
+    F: FnMut(u64) -> io::Result<W>,
+{
+    apbf: APBF<T, H>,
+    sink: W,
+    open: F,
+}
+
+impl<T, H, W, F> WalAPBF<T, H, W, F>
+where
+    T: Hash + ?Sized,
+    H: HashPairs,
+    W: Write,
+    F: FnMut(u64) -> io::Result<W>,
+{
+    /// Wraps `apbf`, which should be empty or replayed from the log,
+    /// opening the segment of its current generation with `open`.
+    pub fn new(apbf: APBF<T, H>, mut open: F) -> io::Result<Self> {
+        let sink = open(apbf.shifts)?;
+        Ok(WalAPBF { apbf, sink, open })
+    }
+
+    // Closes the current segment and starts the one of a new generation.
+    fn start_segment(&mut self) -> io::Result<()> {
+        self.sink.flush()?;
+        self.sink = (self.open)(self.apbf.shifts)?;
+        let mut record = [SHIFT; 9];
+        record[1..].copy_from_slice(&self.apbf.shifts.to_le_bytes());
+        self.sink.write_all(&record)
+    }
+
+    /// Inserts a value and appends it to the log.
+    pub fn insert<V>(&mut self, value: V) -> io::Result<()>
+    where
+        V: Borrow<T>,
+    {
+        let hashes = self.apbf.hashers.hash(value);
+        let shifts = self.apbf.shifts;
+        self.apbf.insert_hashed(&hashes);
+        if self.apbf.shifts != shifts {
+            self.start_segment()?;
+        }
+
+        let (x1, x2) = H::to_pair(&hashes);
+        let mut record = [INSERT; 17];
+        record[1..9].copy_from_slice(&x1.to_le_bytes());
+        record[9..].copy_from_slice(&x2.to_le_bytes());
+        self.sink.write_all(&record)
+    }
+
+    /// Shifts the logical slices, starting a new segment.
+    /// See [`APBF::advance`].
+    pub fn advance(&mut self) -> io::Result<()> {
+        self.apbf.advance();
+        self.start_segment()
+    }
+
+    /// Flushes the current segment.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.sink.flush()
+    }
+
+    /// Returns the underlying structure.
+    pub fn apbf(&self) -> &APBF<T, H> {
+        &self.apbf
+    }
+
+    /// Returns the sink of the current segment, discarding the structure.
+    pub fn into_sink(self) -> W {
+        self.sink
+    }
+}
+
+impl<T: Hash + ?Sized, H: HashPairs, S: BitStore> APBF<T, H, S> {
+    /// Applies a segment of the log written by [`WalAPBF`].
+    ///
+    /// Replaying the remaining segments in order into an empty structure
+    /// restores the state at the time of the last record, advancing over
+    /// the generations of removed segments. A record cut off at the end
+    /// of the segment, as left by a crash during a write, is ignored.
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if an unknown
+    /// record or a shift to a past generation is found.
+    pub fn replay<R: Read>(&mut self, mut log: R) -> io::Result<()> {
+        let mut tag = [0u8];
+        loop {
+            if log.read(&mut tag)? == 0 {
+                return Ok(());
+            }
+            match tag[0] {
+                INSERT => {
+                    let (x1, x2) = match (read_u64(&mut log)?, read_u64(&mut log)?) {
+                        (Some(x1), Some(x2)) => (x1, x2),
+                        _ => return Ok(()),
+                    };
+                    let hashes = self.hashers.hash_pair((x1, x2));
+                    // Shifts are replayed from their own records.
+                    let block = self.block_offset(&hashes);
+                    self.insert_hashes(&hashes, block);
+                }
+                SHIFT => {
+                    let generation = match read_u64(&mut log)? {
+                        Some(g) => g,
+                        None => return Ok(()),
+                    };
+                    if generation <= self.shifts {
+                        return Err(invalid_data(format!(
+                            "shift to generation {} after {}",
+                            generation, self.shifts
+                        )));
+                    }
+                    self.advance_by(generation - self.shifts);
+                }
+                t => return Err(invalid_data(format!("unknown record {}", t))),
+            }
+        }
+    }
+}
+
+// Reads a little-endian integer, or returns `None` at the end of the log.
+fn read_u64<R: Read>(log: &mut R) -> io::Result<Option<u64>> {
+    let mut bytes = [0u8; 8];
+    match log.read_exact(&mut bytes) {
+        Ok(()) => Ok(Some(u64::from_le_bytes(bytes))),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WalAPBF;
+    use crate::hash::split::SplitHashers;
+    use crate::{Builder, APBF};
+    use std::cell::RefCell;
+    use std::io::{self, Write};
+    use std::rc::Rc;
+
+    #[test]
+    fn test_replay() {
+        let (k, l) = (4, 3);
+        let hashers = SplitHashers::with_seed(1000, 0);
+        let make = || {
+            Builder::new(k, l, 1000)
+                .hashers(hashers.clone())
+                .generation(100)
+                .build()
+        };
+
+        let segments = Rc::new(RefCell::new(Vec::new()));
+        let mut wal: WalAPBF<u64, _, _, _> = WalAPBF::new(make(), {
+            let segments = Rc::clone(&segments);
+            move |g| {
+                segments.borrow_mut().push((g, Vec::new()));
+                Ok(Segment(Rc::clone(&segments)))
+            }
+        })
+        .unwrap();
+        for v in 0..1050 {
+            wal.insert(v).unwrap();
+            if v == 500 {
+                wal.advance().unwrap();
+            }
+        }
+        let shifts = wal.apbf().shifts;
+        assert_eq!(shifts, 11);
+
+        // Truncate the last record as if the process crashed while writing it.
+        segments.borrow_mut().last_mut().unwrap().1.pop();
+        // Only the segments of the last k + l generations are needed.
+        let mut apbf: APBF<u64, _> = make();
+        for (g, segment) in segments.borrow().iter() {
+            if g + (k + l) as u64 > shifts {
+                apbf.replay(&segment[..]).unwrap();
+            }
+        }
+        for v in 0..1050 {
+            assert_eq!(apbf.contains(v), wal.apbf().contains(v) && v != 1049);
+        }
+
+        for log in &[&[2u8][..], &[1, 3, 0, 0, 0, 0, 0, 0, 0][..]] {
+            let err = apbf.replay(*log).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_replay_unsized() {
+        let hashers = SplitHashers::with_seed(1000, 0);
+        let apbf = APBF::with_hashers(4, 3, 1000, hashers.clone());
+        let mut wal: WalAPBF<str, _, _, _> = WalAPBF::new(apbf, |_| Ok(Vec::new())).unwrap();
+        wal.insert("apbf").unwrap();
+
+        let mut apbf: APBF<str, _> = APBF::with_hashers(4, 3, 1000, hashers);
+        apbf.replay(&wal.into_sink()[..]).unwrap();
+        assert!(apbf.contains("apbf"));
+    }
+
+    type Segments = Rc<RefCell<Vec<(u64, Vec<u8>)>>>;
+
+    // Appends to the last segment.
+    struct Segment(Segments);
+
+    impl Write for Segment {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().last_mut().unwrap().1.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::borrow::Borrow;
+use std::collections::VecDeque;
+use std::hash::Hash;
+
+use crate::builder::Builder;
+use crate::core::APBF;
+use crate::hash::{HashPairs, Hashers};
+
+impl<T, H> APBF<T, H>
+where
+    T: Hash + ?Sized,
+    H: Hashers,
+{
+    /// Creates a new instance from a builder holding historical values,
+    /// e.g. a set saved by the previous run of a service, as if they had been
+    /// inserted over the past window.
+    ///
+    /// The values are spread evenly over the `l` generations of the window
+    /// in the order given, from the oldest to the newest, so that they expire
+    /// gradually with later insertions rather than all at once. If there are
+    /// more than `l * g` values, only the last `l * g` are loaded.
+    ///
+    /// ```
+    /// use apbf::{Builder, APBF};
+    ///
+    /// let history = vec!["alice", "bob", "carol"];
+    /// let apbf: APBF<&str, _> = APBF::warm_start_from_iter(Builder::new(10, 7, 1024), history);
+    /// assert!(apbf.contains("bob"));
+    /// ```
+    pub fn warm_start_from_iter<I, V>(builder: Builder<T, H>, items: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Borrow<T>,
+    {
+        let mut apbf = builder.build();
+        let hashes = items.into_iter().map(|v| apbf.hashers.hash(v));
+        let hashes = apbf.window_of(hashes);
+        apbf.warm_start(hashes);
+        apbf
+    }
+
+    /// Creates a new instance from a builder holding historical values
+    /// given by pairs of hash values, e.g. ones recorded by
+    /// [`WalAPBF`](crate::WalAPBF). See
+    /// [`warm_start_from_iter`](Self::warm_start_from_iter).
+    pub fn warm_start_from_pairs<I>(builder: Builder<T, H>, pairs: I) -> Self
+    where
+        H: HashPairs,
+        I: IntoIterator<Item = (u64, u64)>,
+    {
+        let mut apbf = builder.build();
+        let hashes = pairs.into_iter().map(|pair| apbf.hashers.hash_pair(pair));
+        let hashes = apbf.window_of(hashes);
+        apbf.warm_start(hashes);
+        apbf
+    }
+
+    // Returns the last `l * g` hash values.
+    fn window_of<I: Iterator<Item = H::H>>(&self, hashes: I) -> VecDeque<H::H> {
+        let window = (self.l as u64).saturating_mul(self.g);
+        let mut last = VecDeque::new();
+        for h in hashes {
+            if last.len() as u64 == window {
+                last.pop_front();
+            }
+            last.push_back(h);
+        }
+        last
+    }
+
+    // Inserts the hash values in up to `l` generations of equal size.
+    fn warm_start(&mut self, hashes: VecDeque<H::H>) {
+        let per = std::cmp::max(hashes.len().div_ceil(self.l), 1);
+        for (i, h) in hashes.iter().enumerate() {
+            if i > 0 && i % per == 0 {
+                self.advance();
+            }
+            let block = self.block_offset(h);
+            self.insert_hashes(h, block);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::sip::SipBuildHasher128;
+    use crate::hash::split::SplitHashers;
+
+    #[test]
+    fn test_warm_start() {
+        // Seeded so that no expired value turns into a false positive.
+        let builder = Builder::new(4, 3, 1 << 20)
+            .hashers(SplitHashers::with_seed(1 << 20, 0))
+            .generation(100);
+        let mut apbf: APBF<u64, _> = APBF::warm_start_from_iter(builder, 0..150);
+        assert_eq!(apbf.shifts, 2);
+        assert!((0..150).all(|v| apbf.contains(v)));
+
+        // The oldest third expires first.
+        apbf.advance_by(2);
+        assert!(!(0..50).any(|v| apbf.contains(v)));
+        assert!((50..150).all(|v| apbf.contains(v)));
+    }
+
+    #[test]
+    fn test_warm_start_window() {
+        let builder = Builder::new(4, 3, 10000)
+            .hashers(SplitHashers::with_seed(10000, 0))
+            .generation(100);
+        let apbf: APBF<u64, _> = APBF::warm_start_from_iter(builder, 0..1000);
+        assert!((700..1000).all(|v| apbf.contains(v)));
+        assert!(!(0..100).any(|v| apbf.contains(v)));
+        assert_eq!(apbf.inserts_since_shift(), 100);
+    }
+
+    #[test]
+    fn test_warm_start_from_pairs() {
+        let hashers = SplitHashers::with_seed(1000, 0);
+        let pairs = (0..100u64)
+            .map(|v| SplitHashers::<SipBuildHasher128>::to_pair(&hashers.hash::<u64, _>(v)))
+            .collect::<Vec<_>>();
+        let builder = Builder::new(4, 3, 1000).hashers(hashers);
+        let apbf: APBF<u64, _> = APBF::warm_start_from_pairs(builder, pairs);
+        assert!((0..100).all(|v| apbf.contains(v)));
+    }
+}
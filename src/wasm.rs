@@ -0,0 +1,99 @@
+//! JavaScript bindings with `wasm-bindgen`.
+//!
+//! The class `Apbf` holds strings and byte arrays. Since
+//! `wasm32-unknown-unknown` has no source of randomness, the seed of the hash
+//! functions is given by the caller, e.g. from `crypto.getRandomValues`:
+//!
+//! ```js
+//! const seed = new BigUint64Array(1);
+//! crypto.getRandomValues(seed);
+//! const filter = new Apbf(10, 7, 1 << 16, seed[0]);
+//! filter.insert("notification-id");
+//! filter.contains("notification-id"); // true
+//! const restored = Apbf.deserialize(filter.serialize());
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+use crate::core::APBF;
+use crate::hash::sip::SipBuildHasher128;
+use crate::hash::split::SplitHashers;
+
+/// APBF instance exported to JavaScript as `Apbf`.
+#[wasm_bindgen(js_name = Apbf)]
+pub struct WasmApbf {
+    apbf: APBF<[u8], SplitHashers<SipBuildHasher128>>,
+}
+
+#[wasm_bindgen(js_class = Apbf)]
+impl WasmApbf {
+    /// Creates a new filter with parameters `k`, `l` and `m` and hash functions
+    /// derived from `seed`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(k: usize, l: usize, m: usize, seed: u64) -> Result<WasmApbf, JsError> {
+        if k == 0 || l == 0 || m == 0 {
+            return Err(JsError::new("parameters must be positive"));
+        }
+        Ok(WasmApbf {
+            apbf: APBF::with_seed(k, l, m, seed),
+        })
+    }
+
+    /// Inserts a string.
+    pub fn insert(&mut self, value: &str) {
+        self.insert_bytes(value.as_bytes());
+    }
+
+    /// Returns `true` if the filter holds a string.
+    pub fn contains(&self, value: &str) -> bool {
+        self.contains_bytes(value.as_bytes())
+    }
+
+    /// Inserts a byte array. A string is the same value as its UTF-8 bytes.
+    #[wasm_bindgen(js_name = insertBytes)]
+    pub fn insert_bytes(&mut self, value: &[u8]) {
+        self.apbf.insert(value);
+    }
+
+    /// Returns `true` if the filter holds a byte array.
+    #[wasm_bindgen(js_name = containsBytes)]
+    pub fn contains_bytes(&self, value: &[u8]) -> bool {
+        self.apbf.contains(value)
+    }
+
+    /// Shifts the logical slices, starting a new generation.
+    pub fn advance(&mut self) {
+        self.apbf.advance();
+    }
+
+    /// Returns the state of the filter including the seed.
+    pub fn serialize(&self) -> Vec<u8> {
+        self.apbf.to_bytes()
+    }
+
+    /// Restores a filter from bytes returned by `serialize`.
+    pub fn deserialize(bytes: &[u8]) -> Result<WasmApbf, JsError> {
+        let apbf = APBF::from_seeded_bytes(bytes).map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(WasmApbf { apbf })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize() {
+        let mut filter = WasmApbf::new(4, 3, 1000, 42).ok().unwrap();
+        for v in 0..100 {
+            filter.insert(&v.to_string());
+        }
+        assert!(filter.contains_bytes(b"42"));
+
+        let restored = WasmApbf::deserialize(&filter.serialize()).ok().unwrap();
+        for v in 0..1000 {
+            let v = v.to_string();
+            assert_eq!(restored.contains(&v), filter.contains(&v));
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+use crate::core::APBF;
+use crate::filter::ProbabilisticFilter;
+use crate::hash::Hashers;
+use crate::snapshot::ApbfSnapshot;
+
+/// The writing half of an APBF shared between threads without locking
+/// the queries.
+///
+/// The writer owns the structure and publishes [snapshots](ApbfSnapshot)
+/// of it with [`publish`](Self::publish). Each [`ApbfReader`] answers queries
+/// from the snapshot it last picked up with [`ApbfReader::refresh`], so
+/// readers never wait for the writer, and see a consistent state which is
+/// as old as they choose. Publishing copies the bit array, so the writer
+/// decides how often to pay for it.
+///
+/// ```
+/// use apbf::{ApbfWriter, APBF};
+///
+/// let mut writer = ApbfWriter::new(APBF::<u64, _>::new(10, 7, 1024));
+/// let mut reader = writer.reader();
+/// writer.insert(42);
+/// assert!(!reader.contains(42));
+///
+/// writer.publish();
+/// reader.refresh();
+/// assert!(reader.contains(42));
+/// ```
+pub struct ApbfWriter<T: ?Sized, H: Hashers> {
+    apbf: APBF<T, H>,
+    latest: Arc<Mutex<Arc<ApbfSnapshot<T, H>>>>,
+}
+
+/// A reading half of an APBF created by [`ApbfWriter::reader`].
+/// Cloning a reader is cheap.
+pub struct ApbfReader<T: ?Sized, H: Hashers> {
+    snapshot: Arc<ApbfSnapshot<T, H>>,
+    latest: Arc<Mutex<Arc<ApbfSnapshot<T, H>>>>,
+}
+
+impl<T, H> ApbfWriter<T, H>
+where
+    T: Hash + ?Sized,
+    H: Hashers + Clone,
+{
+    /// Creates a writer owning `apbf`, whose current state is published.
+    pub fn new(apbf: APBF<T, H>) -> Self {
+        let latest = Arc::new(Mutex::new(Arc::new(apbf.snapshot())));
+        ApbfWriter { apbf, latest }
+    }
+
+    /// Returns a new reader seeing the last published state.
+    pub fn reader(&self) -> ApbfReader<T, H> {
+        ApbfReader {
+            snapshot: Arc::clone(&self.latest.lock().unwrap()),
+            latest: Arc::clone(&self.latest),
+        }
+    }
+
+    /// Inserts a value. It is not visible to readers until published.
+    pub fn insert<V>(&mut self, value: V)
+    where
+        V: Borrow<T>,
+    {
+        self.apbf.insert(value);
+    }
+
+    /// Makes the current state visible to readers refreshing afterwards.
+    ///
+    /// It takes time and memory proportional to `(k + l) * m` to copy
+    /// the bit array. The previous state is released once all readers
+    /// have refreshed.
+    pub fn publish(&mut self) {
+        let snapshot = Arc::new(self.apbf.snapshot());
+        *self.latest.lock().unwrap() = snapshot;
+    }
+
+    /// Returns the structure being written.
+    pub fn apbf(&self) -> &APBF<T, H> {
+        &self.apbf
+    }
+
+    /// Returns the structure being written mutably, e.g. to advance
+    /// its generation.
+    pub fn apbf_mut(&mut self) -> &mut APBF<T, H> {
+        &mut self.apbf
+    }
+}
+
+impl<T, H> ApbfReader<T, H>
+where
+    T: Hash + ?Sized,
+    H: Hashers,
+{
+    /// Returns `true` if the state seen by this reader holds a given value.
+    pub fn contains<V>(&self, value: V) -> bool
+    where
+        V: Borrow<T>,
+    {
+        self.snapshot.contains(value)
+    }
+
+    /// Picks up the state last published by the writer.
+    pub fn refresh(&mut self) {
+        self.snapshot = Arc::clone(&self.latest.lock().unwrap());
+    }
+}
+
+impl<T: ?Sized, H: Hashers> Clone for ApbfReader<T, H> {
+    fn clone(&self) -> Self {
+        ApbfReader {
+            snapshot: Arc::clone(&self.snapshot),
+            latest: Arc::clone(&self.latest),
+        }
+    }
+}
+
+// Queries see the structure being written, including the insertions
+// not published yet.
+impl<T, H> ProbabilisticFilter<T> for ApbfWriter<T, H>
+where
+    T: Hash + ?Sized,
+    H: Hashers + Clone,
+{
+    fn insert(&mut self, value: &T) {
+        ApbfWriter::insert(self, value)
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        self.apbf.contains(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_publish() {
+        let mut writer = ApbfWriter::new(APBF::<u64, _>::new(10, 7, 10000));
+        let readers = (0..4).map(|_| writer.reader()).collect::<Vec<_>>();
+        for v in 0..100 {
+            writer.insert(v);
+        }
+        writer.publish();
+        for v in 100..200 {
+            writer.insert(v);
+        }
+
+        let threads = readers
+            .into_iter()
+            .map(|mut reader| {
+                thread::spawn(move || {
+                    let before = (0..100).any(|v| reader.contains(v));
+                    reader.refresh();
+                    let after = (0..100).all(|v| reader.contains(v));
+                    !before && after
+                })
+            })
+            .collect::<Vec<_>>();
+        for t in threads {
+            assert!(t.join().unwrap());
+        }
+        assert!(writer.apbf().contains(150));
+    }
+}